@@ -0,0 +1,170 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use hound::{SampleFormat, WavSpec, WavWriter};
+use mel_feature_extractor_rust::dsp::{apply_mel_filters, apply_rfft, mel_filter_bank, power_spectrogram};
+use mel_feature_extractor_rust::{
+    extract_mel_spectrogram_from_bytes, resample_audio_streaming, MelConfig, ResamplerState,
+};
+use realfft::RealFftPlanner;
+use std::io::Cursor;
+
+const N_MELS: [usize; 3] = [40, 80, 128];
+
+fn sine(seconds: usize, sample_rate: u32, frequency: f32) -> Vec<f32> {
+    (0..seconds * sample_rate as usize)
+        .map(|i| 0.5 * (2.0 * std::f32::consts::PI * frequency * i as f32 / sample_rate as f32).sin())
+        .collect()
+}
+
+fn sine_wav_bytes(seconds: usize, sample_rate: u32, frequency: f32) -> Vec<u8> {
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let mut cursor = Cursor::new(Vec::new());
+    let mut writer = WavWriter::new(&mut cursor, spec).unwrap();
+    for sample in sine(seconds, sample_rate, frequency) {
+        writer.write_sample((sample * i16::MAX as f32) as i16).unwrap();
+    }
+    writer.finalize().unwrap();
+    cursor.into_inner()
+}
+
+fn config_with_mels(n_mels: usize) -> MelConfig {
+    MelConfig::builder().n_mels(n_mels).build().unwrap()
+}
+
+fn bench_resample(c: &mut Criterion) {
+    let mut group = c.benchmark_group("resample_1s");
+    for orig_rate in [16000, 44100] {
+        let samples = sine(1, orig_rate, 440.0);
+        group.bench_with_input(BenchmarkId::from_parameter(orig_rate), &samples, |b, samples| {
+            b.iter(|| {
+                let mut state = ResamplerState::new();
+                let mut output = resample_audio_streaming(samples, &mut state, orig_rate, 16000).unwrap();
+                output.extend(state.flush().unwrap());
+                output
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_rfft(c: &mut Criterion) {
+    let config = MelConfig::whisper_default();
+    let r2c = RealFftPlanner::<f32>::new().plan_fft_forward(config.frame_length);
+    let frame = sine(1, 16000, 440.0)[..config.frame_length].to_vec();
+
+    c.bench_function("apply_rfft_single_frame", |b| {
+        b.iter(|| apply_rfft(vec![frame.clone()], r2c.as_ref()).unwrap())
+    });
+}
+
+fn bench_filter_bank(c: &mut Criterion) {
+    let mut group = c.benchmark_group("mel_filter_bank");
+    for n_mels in N_MELS {
+        let config = config_with_mels(n_mels);
+        group.bench_with_input(BenchmarkId::from_parameter(n_mels), &config, |b, config| {
+            b.iter(|| mel_filter_bank(config).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_mel_projection(c: &mut Criterion) {
+    let mut group = c.benchmark_group("apply_mel_filters_3000_frames");
+    let whisper = MelConfig::whisper_default();
+    let r2c = RealFftPlanner::<f32>::new().plan_fft_forward(whisper.frame_length);
+    let frame = sine(1, 16000, 440.0)[..whisper.frame_length].to_vec();
+    let power_spec = power_spectrogram(apply_rfft(vec![frame; 3001], r2c.as_ref()).unwrap()).unwrap();
+    for n_mels in N_MELS {
+        let config = config_with_mels(n_mels);
+        let filters = mel_filter_bank(&config).unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(n_mels), &config, |b, config| {
+            b.iter(|| apply_mel_filters(&power_spec, &filters, config).unwrap())
+        });
+    }
+    group.finish();
+}
+
+/// Filter-major `Vec<Vec<f32>>` projection as it was before the bank was flattened to
+/// bin-major rows: one strided dot product per filter, each row its own allocation.
+fn project_filter_major(spectrum: &[f32], filters: &[Vec<f32>]) -> Vec<f32> {
+    filters
+        .iter()
+        .map(|filter| spectrum.iter().zip(filter).map(|(power, weight)| power * weight).sum())
+        .collect()
+}
+
+fn bench_projection_layout(c: &mut Criterion) {
+    let mut group = c.benchmark_group("mel_projection_layout_3000_frames");
+    let whisper = MelConfig::whisper_default();
+    let r2c = RealFftPlanner::<f32>::new().plan_fft_forward(whisper.frame_length);
+    let frame = sine(1, 16000, 440.0)[..whisper.frame_length].to_vec();
+    let power_spec = power_spectrogram(apply_rfft(vec![frame; 3000], r2c.as_ref()).unwrap()).unwrap();
+    for n_mels in N_MELS {
+        let config = config_with_mels(n_mels);
+        let bank = mel_filter_bank(&config).unwrap();
+        let rows: Vec<&[f32]> = bank.rows().collect();
+        let filter_major: Vec<Vec<f32>> = (0..n_mels)
+            .map(|mel| rows.iter().map(|row| row[mel]).collect())
+            .collect();
+
+        group.bench_with_input(BenchmarkId::new("nested_filter_major", n_mels), &power_spec, |b, spec| {
+            b.iter(|| {
+                spec.iter()
+                    .map(|frame| project_filter_major(&frame[..config.n_fft], &filter_major))
+                    .collect::<Vec<_>>()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("flat_bin_major", n_mels), &power_spec, |b, spec| {
+            b.iter(|| spec.iter().map(|frame| bank.project(&frame[..config.n_fft])).collect::<Vec<_>>())
+        });
+    }
+    group.finish();
+}
+
+fn bench_extract(c: &mut Criterion) {
+    let mut group = c.benchmark_group("extract_mel_spectrogram_30s");
+    let wav = sine_wav_bytes(30, 16000, 440.0);
+    for n_mels in N_MELS {
+        let config = config_with_mels(n_mels);
+        group.bench_with_input(BenchmarkId::from_parameter(n_mels), &config, |b, config| {
+            b.iter(|| extract_mel_spectrogram_from_bytes(&wav, config).unwrap())
+        });
+    }
+    group.finish();
+}
+
+/// Whole-file extraction on rayon pools of 1 and 4 threads; the `parallel` feature is expected
+/// to at least halve the time on 4 cores. Run with
+/// `cargo bench --features parallel -- extract_30s_threads`.
+#[cfg(feature = "parallel")]
+fn bench_parallel_speedup(c: &mut Criterion) {
+    let mut group = c.benchmark_group("extract_30s_threads");
+    let wav = sine_wav_bytes(30, 16000, 440.0);
+    let config = MelConfig::whisper_default();
+    for threads in [1, 4] {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(threads), &wav, |b, wav| {
+            b.iter(|| pool.install(|| extract_mel_spectrogram_from_bytes(wav, &config).unwrap()))
+        });
+    }
+    group.finish();
+}
+
+#[cfg(not(feature = "parallel"))]
+fn bench_parallel_speedup(_c: &mut Criterion) {}
+
+criterion_group!(
+    benches,
+    bench_resample,
+    bench_rfft,
+    bench_filter_bank,
+    bench_mel_projection,
+    bench_projection_layout,
+    bench_extract,
+    bench_parallel_speedup,
+);
+criterion_main!(benches);
@@ -0,0 +1,96 @@
+//! Training-time augmentations applied to `[n_frames][n_mels]` feature matrices.
+
+use rand::Rng;
+use rand_distr::{Beta, Distribution, StandardNormal};
+
+use crate::MelError;
+
+/// Masking parameters for [`spec_augment`], following Park et al. (2019).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpecAugmentConfig {
+    pub n_time_masks: usize,
+    /// Each time mask covers a uniformly drawn `0..=time_mask_param` frames.
+    pub time_mask_param: usize,
+    pub n_freq_masks: usize,
+    /// Each frequency mask covers a uniformly drawn `0..=freq_mask_param` mel bins.
+    pub freq_mask_param: usize,
+    pub mask_value: f32,
+}
+
+impl Default for SpecAugmentConfig {
+    /// The paper's LibriSpeech basic policy, without time warping.
+    fn default() -> Self {
+        SpecAugmentConfig {
+            n_time_masks: 1,
+            time_mask_param: 100,
+            n_freq_masks: 1,
+            freq_mask_param: 27,
+            mask_value: 0.0,
+        }
+    }
+}
+
+/// Sets randomly placed runs of whole frames and whole mel bins to `config.mask_value`. Masks
+/// wider than the spectrogram are clamped to it.
+pub fn spec_augment(spectrogram: &mut [Vec<f32>], config: &SpecAugmentConfig, rng: &mut impl Rng) {
+    let n_frames = spectrogram.len();
+    let n_mels = spectrogram.first().map_or(0, Vec::len);
+
+    for _ in 0..config.n_freq_masks {
+        let (start, width) = random_mask(n_mels, config.freq_mask_param, rng);
+        for frame in spectrogram.iter_mut() {
+            let end = (start + width).min(frame.len());
+            frame[start.min(end)..end].fill(config.mask_value);
+        }
+    }
+
+    for _ in 0..config.n_time_masks {
+        let (start, width) = random_mask(n_frames, config.time_mask_param, rng);
+        for frame in spectrogram[start..start + width].iter_mut() {
+            frame.fill(config.mask_value);
+        }
+    }
+}
+
+/// A `(start, width)` span within `0..len` with `width` drawn from `0..=max_width`.
+fn random_mask(len: usize, max_width: usize, rng: &mut impl Rng) -> (usize, usize) {
+    let width = rng.random_range(0..=max_width.min(len));
+    let start = rng.random_range(0..=len - width);
+    (start, width)
+}
+
+/// Mixup of two equally shaped feature matrices: `lambda * a + (1 - lambda) * b`.
+pub fn mixup_spectrograms(a: &[Vec<f32>], b: &[Vec<f32>], lambda: f32) -> Result<Vec<Vec<f32>>, MelError> {
+    if a.len() != b.len() || a.iter().zip(b).any(|(x, y)| x.len() != y.len()) {
+        return Err(MelError::InvalidConfig("Mixup inputs must have the same shape".to_string()));
+    }
+
+    Ok(a.iter()
+        .zip(b)
+        .map(|(x, y)| x.iter().zip(y).map(|(p, q)| lambda * p + (1.0 - lambda) * q).collect())
+        .collect())
+}
+
+/// Draws a mixup weight from `Beta(alpha, alpha)`. A non-positive `alpha` disables mixup by
+/// returning 1, as in the reference implementation.
+pub fn random_lambda(alpha: f64, rng: &mut impl Rng) -> f32 {
+    Beta::new(alpha, alpha).map_or(1.0, |beta| beta.sample(rng) as f32)
+}
+
+/// Adds white Gaussian noise scaled so that the signal-to-noise ratio is `snr_db`, measured
+/// against the mean power of `samples`. Silent input and `snr_db = f32::INFINITY` are left
+/// unchanged.
+pub fn add_gaussian_noise(samples: &mut [f32], snr_db: f32, rng: &mut impl Rng) {
+    if samples.is_empty() {
+        return;
+    }
+    let signal_power = samples.iter().map(|&x| x as f64 * x as f64).sum::<f64>() / samples.len() as f64;
+    let noise_std = (signal_power / 10f64.powf(snr_db as f64 / 10.0)).sqrt();
+    if !(noise_std > 0.0 && noise_std.is_finite()) {
+        return;
+    }
+    for sample in samples.iter_mut() {
+        let noise: f64 = rng.sample(StandardNormal);
+        *sample += (noise_std * noise) as f32;
+    }
+}
@@ -0,0 +1,87 @@
+use std::error::Error;
+use std::io::Read;
+use std::path::Path;
+use std::process::ExitCode;
+
+use clap::Parser;
+use mel_feature_extractor_rust::{
+    extract_mel_spectrogram, extract_mel_spectrogram_from_bytes, save_as_npy, save_matrix_as_csv, MelConfig,
+    MelScale,
+};
+
+/// Extracts a log-mel spectrogram from a WAV file and writes it as CSV and/or NumPy `.npy`,
+/// laid out as `n_mels` rows by `n_frames` columns.
+#[derive(Parser)]
+#[command(name = "mel-extract", version)]
+struct Args {
+    /// WAV file to read, or `-` for stdin.
+    #[arg(long, default_value = "-")]
+    input: String,
+    /// Output file, written as NumPy for a `.npy` extension and as CSV for `.csv`.
+    #[arg(long)]
+    output: Option<String>,
+    #[arg(long)]
+    output_csv: Option<String>,
+    #[arg(long)]
+    output_npy: Option<String>,
+    #[arg(long, default_value_t = 80)]
+    n_mels: usize,
+    /// Sample rate the audio is resampled to before analysis; 30 s of it are kept.
+    #[arg(long, default_value_t = 16000)]
+    target_sr: u32,
+    #[arg(long, default_value_t = 160)]
+    hop_length: usize,
+    #[arg(long, default_value_t = 400)]
+    frame_length: usize,
+    #[arg(long, default_value = "slaney", value_parser = ["slaney", "htk"])]
+    mel_scale: String,
+}
+
+fn main() -> ExitCode {
+    match run(Args::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("mel-extract: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Args) -> Result<(), Box<dyn Error>> {
+    let config = MelConfig::builder()
+        .sample_rate(args.target_sr)
+        .n_mels(args.n_mels)
+        .hop_length(args.hop_length)
+        .frame_length(args.frame_length)
+        .n_fft(args.frame_length / 2 + 1)
+        .max_freq(args.target_sr as f32 / 2.0)
+        .mel_scale(if args.mel_scale == "htk" { MelScale::Htk } else { MelScale::Slaney })
+        .build()?;
+
+    let spectrogram = if args.input == "-" {
+        let mut bytes = Vec::new();
+        std::io::stdin().read_to_end(&mut bytes)?;
+        extract_mel_spectrogram_from_bytes(&bytes, &config)?
+    } else {
+        extract_mel_spectrogram(&args.input, &config)?
+    };
+
+    let (n_frames, n_mels) = (spectrogram.n_frames(), spectrogram.n_mels());
+    let rows: Vec<Vec<f32>> = spectrogram.iter_mel_bins().collect();
+
+    if let Some(path) = &args.output {
+        match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+            Some("npy") => save_as_npy(&rows, path)?,
+            Some("csv") => save_matrix_as_csv(&rows, path, None)?,
+            _ => return Err(format!("cannot tell the format of {}; use a .npy or .csv extension", path).into()),
+        }
+    }
+    if let Some(path) = &args.output_csv {
+        save_matrix_as_csv(&rows, path, None)?;
+    }
+    if let Some(path) = &args.output_npy {
+        save_as_npy(&rows, path)?;
+    }
+    eprintln!("{} mels x {} frames", n_mels, n_frames);
+    Ok(())
+}
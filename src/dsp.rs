@@ -0,0 +1,944 @@
+//! The individual stages of the extraction pipeline, in the order [`MelExtractorContext`] runs
+//! them, for callers composing their own variant.
+//!
+//! [`MelExtractorContext`]: crate::MelExtractorContext
+//!
+//! Only needs `alloc`, so it is all that is compiled without the `std` feature. Such builds
+//! take audio as `&[f32]` at the model's sample rate and transform it with [`MixedRadixFft`].
+
+#[cfg(not(feature = "std"))]
+use alloc::{format, string::ToString, vec, vec::Vec};
+use num_complex::Complex;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+#[cfg(feature = "std")]
+use realfft::RealToComplex;
+
+use crate::{DrcConfig, LogBase, MelConfig, MelError, MelScale, PcenConfig, WindowType};
+
+/// Splits `samples` into overlapping `config.frame_length` frames every `config.hop_length`
+/// samples, dropping any trailing partial frame.
+pub fn frame_signal(samples: Vec<f32>, config: &MelConfig) -> Result<Vec<Vec<f32>>, MelError> {
+    Ok(frame_signal_views(&samples, config.frame_length, config.hop_length).map(<[f32]>::to_vec).collect())
+}
+
+/// The frames of [`frame_signal`] as slices of `samples`, without copying. Panics if
+/// `frame_length` or `hop_length` is zero.
+pub fn frame_signal_views(samples: &[f32], frame_length: usize, hop_length: usize) -> impl Iterator<Item = &[f32]> {
+    samples.windows(frame_length).step_by(hop_length)
+}
+
+/// Builds a periodic window of `len` coefficients (denominator `len`, as in `torch.hann_window`).
+pub fn make_window(len: usize, kind: WindowType) -> Vec<f32> {
+    let coefficients: &[f32] = match kind {
+        WindowType::Rectangular => return vec![1.0; len],
+        WindowType::Hann => &[0.5, 0.5],
+        WindowType::Hamming => &[0.54, 0.46],
+        WindowType::Blackman => &[0.42, 0.5, 0.08],
+        WindowType::BlackmanHarris => &[0.35875, 0.48829, 0.14128, 0.01168],
+        WindowType::Flattop => &[0.21557895, 0.41663158, 0.27726316, 0.08357895, 0.006947368],
+    };
+
+    (0..len)
+        .map(|i| {
+            let x = 2.0 * core::f32::consts::PI * i as f32 / len as f32;
+            coefficients.iter()
+                .enumerate()
+                .skip(1)
+                .fold(coefficients[0], |acc, (k, a)| {
+                    let term = a * (k as f32 * x).cos();
+                    if k % 2 == 1 { acc - term } else { acc + term }
+                })
+        })
+        .collect()
+}
+
+/// Multiplies every frame element-wise by `window`.
+pub fn apply_window(mut frames: Vec<Vec<f32>>, window: &[f32]) -> Result<Vec<Vec<f32>>, MelError> {
+    #[cfg(feature = "parallel")]
+    let frames_iter = frames.par_iter_mut();
+    #[cfg(not(feature = "parallel"))]
+    let frames_iter = frames.iter_mut();
+
+    frames_iter.for_each(|frame| {
+        for (i, sample) in frame.iter_mut().enumerate() {
+            *sample *= window[i]
+        }
+    });
+
+    Ok(frames)
+}
+
+/// A forward real FFT of one fixed length, producing `len / 2 + 1` bins.
+pub trait RealFft: Sync {
+    /// The number of real input samples.
+    fn frame_length(&self) -> usize;
+
+    /// Transforms `input`, which may be used as scratch space, into `output`.
+    fn process(&self, input: &mut [f32], output: &mut [Complex<f32>]) -> Result<(), MelError>;
+}
+
+#[cfg(feature = "std")]
+impl RealFft for dyn RealToComplex<f32> {
+    fn frame_length(&self) -> usize {
+        RealToComplex::len(self)
+    }
+
+    fn process(&self, input: &mut [f32], output: &mut [Complex<f32>]) -> Result<(), MelError> {
+        Ok(RealToComplex::process(self, input, output)?)
+    }
+}
+
+/// Mixed-radix Cooley-Tukey FFT of any length, for builds without `realfft`. Prime factors are
+/// transformed directly, so lengths with large prime factors approach `O(n²)`.
+#[derive(Debug, Clone)]
+pub struct MixedRadixFft {
+    factors: Vec<usize>,
+    twiddles: Vec<Complex<f32>>,
+}
+
+impl MixedRadixFft {
+    pub fn new(len: usize) -> Self {
+        let mut factors = Vec::new();
+        let mut remaining = len;
+        let mut factor = 2;
+        while remaining > 1 {
+            while remaining.is_multiple_of(factor) {
+                factors.push(factor);
+                remaining /= factor;
+            }
+            factor += 1;
+        }
+        let twiddles = (0..len)
+            .map(|k| {
+                let angle = -2.0 * core::f64::consts::PI * k as f64 / len as f64;
+                Complex::new(angle.cos() as f32, angle.sin() as f32)
+            })
+            .collect();
+        MixedRadixFft { factors, twiddles }
+    }
+}
+
+impl RealFft for MixedRadixFft {
+    fn frame_length(&self) -> usize {
+        self.twiddles.len()
+    }
+
+    fn process(&self, input: &mut [f32], output: &mut [Complex<f32>]) -> Result<(), MelError> {
+        let len = self.twiddles.len();
+        if input.len() != len || output.len() != len / 2 + 1 {
+            return Err(MelError::InvalidConfig(format!(
+                "FFT of length {} needs {} inputs and {} outputs, got {} and {}",
+                len, len, len / 2 + 1, input.len(), output.len()
+            )));
+        }
+        let signal: Vec<Complex<f32>> = input.iter().map(|&x| Complex::new(x, 0.0)).collect();
+        let mut spectrum = vec![Complex::new(0.0, 0.0); len];
+        mixed_radix_fft(&signal, 1, &mut spectrum, &self.factors, &self.twiddles, 1);
+        output.copy_from_slice(&spectrum[..len / 2 + 1]);
+        Ok(())
+    }
+}
+
+/// Decimation in time: `output.len()` points of `input` taken every `stride`, split into
+/// `factors[0]` interleaved sub-sequences whose transforms are combined with `twiddles`, the
+/// full-length roots of unity, read every `twiddle_step`.
+fn mixed_radix_fft(
+    input: &[Complex<f32>],
+    stride: usize,
+    output: &mut [Complex<f32>],
+    factors: &[usize],
+    twiddles: &[Complex<f32>],
+    twiddle_step: usize,
+) {
+    let Some((&radix, rest)) = factors.split_first() else {
+        output[0] = input[0];
+        return;
+    };
+    let sub_len = output.len() / radix;
+    for (q, sub_output) in output.chunks_exact_mut(sub_len).enumerate() {
+        mixed_radix_fft(&input[q * stride..], stride * radix, sub_output, rest, twiddles, twiddle_step * radix);
+    }
+
+    let mut column = vec![Complex::new(0.0, 0.0); radix];
+    for k in 0..sub_len {
+        for (q, value) in column.iter_mut().enumerate() {
+            *value = output[q * sub_len + k];
+        }
+        for r in 0..radix {
+            let bin = k + r * sub_len;
+            output[bin] = column.iter()
+                .enumerate()
+                .map(|(q, &value)| value * twiddles[(q * bin * twiddle_step) % twiddles.len()])
+                .sum();
+        }
+    }
+}
+
+/// Real FFT of every frame; `r2c` must be planned for the frame length. Takes a `realfft` plan
+/// (as `&dyn RealToComplex<f32>`) or a [`MixedRadixFft`].
+pub fn apply_rfft<F: RealFft + ?Sized>(frames: Vec<Vec<f32>>, r2c: &F) -> Result<Vec<Vec<Complex<f32>>>, MelError> {
+    let process_frame = |mut frame: Vec<f32>| -> Result<Vec<Complex<f32>>, MelError> {
+        let mut output = vec![Complex::new(0.0, 0.0); r2c.frame_length() / 2 + 1];
+        r2c.process(&mut frame, &mut output)?;
+        Ok(output)
+    };
+
+    #[cfg(feature = "parallel")]
+    let spectrogram = frames.into_par_iter().map(process_frame).collect();
+    #[cfg(not(feature = "parallel"))]
+    let spectrogram = frames.into_iter().map(process_frame).collect();
+
+    spectrogram
+}
+
+/// `|X|^2` of every bin.
+pub fn power_spectrogram(spectrogram: Vec<Vec<Complex<f32>>>) -> Result<Vec<Vec<f32>>, MelError> {
+    #[cfg(feature = "parallel")]
+    let frames_iter = spectrogram.into_par_iter();
+    #[cfg(not(feature = "parallel"))]
+    let frames_iter = spectrogram.into_iter();
+
+    Ok(frames_iter
+        .map(|frame|
+            frame.into_iter()
+                .map(|c| c.norm_sqr())
+                .collect()
+        ).collect()
+    )
+}
+
+/// `|X|` of every bin.
+pub fn magnitude_spectrogram(spectrogram: Vec<Vec<Complex<f32>>>) -> Vec<Vec<f32>> {
+    #[cfg(feature = "parallel")]
+    let frames_iter = spectrogram.into_par_iter();
+    #[cfg(not(feature = "parallel"))]
+    let frames_iter = spectrogram.into_iter();
+
+    frames_iter
+        .map(|frame| frame.into_iter().map(|c| c.norm()).collect())
+        .collect()
+}
+
+/// Centre frequency in Hz of each of the `n_fft` rfft bins.
+pub fn fft_frequencies(n_fft: usize, sample_rate: u32) -> Vec<f32> {
+    linspace(0.0, sample_rate as f32 / 2.0, n_fft)
+}
+
+/// Filter weights stored row-major as `[n_fft_bins][n_mels]` in one allocation, so the weights
+/// of every filter at a given FFT bin are adjacent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MelFilterBank {
+    data: Vec<f32>,
+    n_fft_bins: usize,
+    n_mels: usize,
+}
+
+impl MelFilterBank {
+    /// Weight of filter `m` at FFT bin `k`.
+    pub fn get(&self, k: usize, m: usize) -> f32 {
+        self.data[k * self.n_mels + m]
+    }
+
+    pub fn n_fft_bins(&self) -> usize {
+        self.n_fft_bins
+    }
+
+    pub fn n_mels(&self) -> usize {
+        self.n_mels
+    }
+
+    pub fn as_slice(&self) -> &[f32] {
+        &self.data
+    }
+
+    /// The `n_mels` weights of each FFT bin in turn.
+    pub fn rows(&self) -> impl Iterator<Item = &[f32]> {
+        self.data.chunks_exact(self.n_mels)
+    }
+
+    /// Projects one spectrum frame onto the filters, using its first `n_fft_bins` bins.
+    pub fn project(&self, spectrum: &[f32]) -> Vec<f32> {
+        // Accumulate one bin at a time so the filter weights are read sequentially.
+        let mut mel_frame = vec![0.0f32; self.n_mels];
+        for (&power, weights) in spectrum.iter().zip(self.rows()) {
+            scaled_add(&mut mel_frame, power, weights);
+        }
+        mel_frame
+    }
+}
+
+/// `acc += scale * x`, eight lanes at a time.
+#[cfg(feature = "simd")]
+fn scaled_add(acc: &mut [f32], scale: f32, x: &[f32]) {
+    use wide::f32x8;
+
+    let scale_lanes = f32x8::splat(scale);
+    let mut acc_chunks = acc.chunks_exact_mut(8);
+    let mut x_chunks = x.chunks_exact(8);
+    for (a, b) in (&mut acc_chunks).zip(&mut x_chunks) {
+        let sum = f32x8::from(<[f32; 8]>::try_from(b).unwrap())
+            .mul_add(scale_lanes, f32x8::from(<[f32; 8]>::try_from(&*a).unwrap()));
+        a.copy_from_slice(sum.as_array_ref());
+    }
+    for (a, b) in acc_chunks.into_remainder().iter_mut().zip(x_chunks.remainder()) {
+        *a += scale * b;
+    }
+}
+
+#[cfg(not(feature = "simd"))]
+fn scaled_add(acc: &mut [f32], scale: f32, x: &[f32]) {
+    for (a, b) in acc.iter_mut().zip(x) {
+        *a += scale * b;
+    }
+}
+
+/// `[n_fft][n_filters]` triangular filters whose edges and centres are `filter_freqs`
+/// (`n_filters + 2` ascending frequencies), evaluated at `fft_freqs`.
+pub fn create_triangular_filter_bank(fft_freqs: &[f32], filter_freqs: &[f32]) -> Result<MelFilterBank, MelError> {
+    if filter_freqs.len() < 3 {
+        return Err(MelError::InvalidConfig("Number of mel filters must be positive.".to_string()));
+    }
+
+    let filter_edges: Vec<[f32; 3]> = filter_freqs.windows(3).map(|edges| [edges[0], edges[1], edges[2]]).collect();
+    Ok(triangular_filters(fft_freqs, &filter_edges))
+}
+
+/// One triangular filter per `[left, centre, right]` in `filter_edges`, evaluated at `fft_freqs`.
+fn triangular_filters(fft_freqs: &[f32], filter_edges: &[[f32; 3]]) -> MelFilterBank {
+    let num_frequency_bins = fft_freqs.len();
+    let num_mel_filters = filter_edges.len();
+
+    let mut mel_filters = vec![0.0f32; num_frequency_bins * num_mel_filters];
+
+    for (k, &fft_freq) in fft_freqs.iter().enumerate() {
+        for (m, &[left, centre, right]) in filter_edges.iter().enumerate() {
+            let down_slope = if centre - left != 0.0 {
+                -(left - fft_freq) / (centre - left)
+            } else {
+                0.0
+            };
+            let up_slope = if right - centre != 0.0 {
+                (right - fft_freq) / (right - centre)
+            } else {
+                0.0
+            };
+            mel_filters[k * num_mel_filters + m] = down_slope.min(up_slope).max(0.0);
+        }
+    }
+
+    MelFilterBank { data: mel_filters, n_fft_bins: num_frequency_bins, n_mels: num_mel_filters }
+}
+
+/// The `[n_fft][n_mels]` mel filter bank described by `config`.
+pub fn mel_filter_bank(config: &MelConfig) -> Result<MelFilterBank, MelError> {
+    build_mel_filter_bank(config).map(|(mel_filters, _)| mel_filters)
+}
+
+/// A Slaney-scale, Slaney-normalised filter bank together with each filter's normalisation
+/// factor, `2 / (right edge - left edge)` in Hz, so every filter has unit area over frequency.
+pub fn mel_filter_bank_with_norms(
+    n_fft: usize,
+    n_mels: usize,
+    min_freq: f32,
+    max_freq: f32,
+    sr: u32,
+) -> Result<(MelFilterBank, Vec<f32>), MelError> {
+    let config = MelConfig {
+        sample_rate: sr,
+        n_fft,
+        n_mels,
+        min_freq,
+        max_freq,
+        mel_scale: MelScale::Slaney,
+        use_slaney_norm: true,
+        ..MelConfig::whisper_default()
+    };
+    build_mel_filter_bank(&config)
+}
+
+/// The filter bank for `config` and the Slaney factors, applied only when `use_slaney_norm` is set.
+fn build_mel_filter_bank(config: &MelConfig) -> Result<(MelFilterBank, Vec<f32>), MelError> {
+    let filter_edges_hz = mel_filter_edges(config)?;
+    let fft_freqs_hz = fft_frequencies(config.n_fft, config.sample_rate);
+    Ok(normalized_filters(&fft_freqs_hz, &filter_edges_hz, config.use_slaney_norm))
+}
+
+/// `[left, centre, right]` in Hz of each filter described by `config`.
+fn mel_filter_edges(config: &MelConfig) -> Result<Vec<[f32; 3]>, MelError> {
+    let num_mel_filters = config.n_mels;
+    if num_mel_filters < 1 {
+        return Err(MelError::InvalidConfig("Mel filter bank needs at least one filter".to_string()));
+    }
+    let mel_min = config.mel_scale.hertz_to_mel(config.min_freq);
+    let mel_max = config.mel_scale.hertz_to_mel(config.max_freq);
+
+    let mel_freqs_vec = linspace(mel_min, mel_max, num_mel_filters + 2);
+
+    let mut filter_freqs_hz: Vec<f32> = Vec::with_capacity(mel_freqs_vec.len());
+
+    for mel in mel_freqs_vec.iter() {
+        filter_freqs_hz.push(config.mel_scale.mel_to_hertz(*mel));
+    }
+
+    // Overlap widens each side of a filter about its centre on the mel axis.
+    let widen = 1.0 + config.filter_overlap;
+    Ok(mel_freqs_vec.windows(3)
+        .zip(filter_freqs_hz.windows(3))
+        .map(|(mel, hz)| {
+            if config.filter_overlap == 0.0 {
+                return [hz[0], hz[1], hz[2]];
+            }
+            let left = config.mel_scale.mel_to_hertz(mel[1] - (mel[1] - mel[0]) * widen);
+            let right = config.mel_scale.mel_to_hertz(mel[1] + (mel[2] - mel[1]) * widen);
+            [left, hz[1], right]
+        })
+        .collect())
+}
+
+/// Triangular filters over `filter_edges_hz` and their Slaney factors, applied when `use_slaney_norm`.
+fn normalized_filters(fft_freqs_hz: &[f32], filter_edges_hz: &[[f32; 3]], use_slaney_norm: bool) -> (MelFilterBank, Vec<f32>) {
+    let num_mel_filters = filter_edges_hz.len();
+    let mut mel_filters = triangular_filters(fft_freqs_hz, filter_edges_hz);
+
+    let mut slaney_norm_factors = Vec::with_capacity(num_mel_filters);
+    for (i, &[left_hz, _, right_hz]) in filter_edges_hz.iter().enumerate() {
+        let width = right_hz - left_hz;
+
+        if width > f32::EPSILON {
+            slaney_norm_factors.push(2.0 / width);
+        } else {
+            if use_slaney_norm {
+                warn_zero_width_filter(i, width);
+            }
+            slaney_norm_factors.push(0.0);
+        }
+    }
+
+    if use_slaney_norm {
+        for row in mel_filters.data.chunks_exact_mut(num_mel_filters) {
+            for m in 0..num_mel_filters {
+                row[m] *= slaney_norm_factors[m];
+            }
+        }
+    }
+
+    (mel_filters, slaney_norm_factors)
+}
+
+#[cfg(feature = "std")]
+fn warn_zero_width_filter(index: usize, width: f32) {
+    eprintln!("Warning: Mel filter {} has zero or negative width ({}), setting norm factor to 0.", index, width);
+}
+
+#[cfg(not(feature = "std"))]
+fn warn_zero_width_filter(_index: usize, _width: f32) {}
+
+/// Kaldi's default lower inflection point of the VTLN warp (`--vtln-low`), in Hz.
+const VTLN_LOW_CUTOFF_HZ: f32 = 100.0;
+/// Kaldi's default distance of the upper inflection point below Nyquist (`--vtln-high=-500`),
+/// in Hz.
+const VTLN_HIGH_CUTOFF_OFFSET_HZ: f32 = 500.0;
+
+/// Rebuilds `filterbank` for a speaker with warp factor `warp_alpha` (typically 0.85 to 1.15)
+/// by moving every filter edge of `config` through the piecewise-linear VTLN warp of Lee and
+/// Rose as implemented in Kaldi: frequencies scale by `1 / warp_alpha` between the inflection
+/// points at 100 Hz and 500 Hz below Nyquist, and map linearly onto `min_freq` and `max_freq`
+/// outside them. `warp_alpha == 1.0` reproduces the unwarped bank.
+///
+/// Like Kaldi, this rejects a band `[min_freq, max_freq]` that does not strictly contain both
+/// inflection points.
+pub fn vtln_warp_mel_filterbank(
+    filterbank: &MelFilterBank,
+    warp_alpha: f32,
+    config: &MelConfig,
+) -> Result<MelFilterBank, MelError> {
+    if !(warp_alpha.is_finite() && warp_alpha > 0.0) {
+        return Err(MelError::InvalidConfig(format!("VTLN warp factor must be positive, got {}", warp_alpha)));
+    }
+    if filterbank.n_mels != config.n_mels {
+        return Err(MelError::InvalidConfig(format!(
+            "Filter bank has {} mel filters but the config describes {}",
+            filterbank.n_mels, config.n_mels
+        )));
+    }
+    let low_cutoff = VTLN_LOW_CUTOFF_HZ;
+    let high_cutoff = config.sample_rate as f32 / 2.0 - VTLN_HIGH_CUTOFF_OFFSET_HZ;
+    if !(config.min_freq < low_cutoff && low_cutoff < high_cutoff && high_cutoff < config.max_freq) {
+        return Err(MelError::InvalidConfig(format!(
+            "VTLN inflection points {} Hz and {} Hz must lie inside the mel band ({} Hz, {} Hz)",
+            low_cutoff, high_cutoff, config.min_freq, config.max_freq
+        )));
+    }
+
+    let filter_edges_hz = mel_filter_edges(config)?;
+    let warped_edges: Vec<[f32; 3]> = filter_edges_hz.iter()
+        .map(|edges| {
+            edges.map(|freq| vtln_warp_freq(freq, warp_alpha, low_cutoff, high_cutoff, config.min_freq, config.max_freq))
+        })
+        .collect();
+    let fft_freqs_hz = fft_frequencies(filterbank.n_fft_bins, config.sample_rate);
+    Ok(normalized_filters(&fft_freqs_hz, &warped_edges, config.use_slaney_norm).0)
+}
+
+/// The piecewise-linear VTLN warp of `freq` within `[low_freq, high_freq]`, Kaldi's
+/// `MelBanks::VtlnWarpFreq`.
+fn vtln_warp_freq(
+    freq: f32,
+    warp_alpha: f32,
+    vtln_low: f32,
+    vtln_high: f32,
+    low_freq: f32,
+    high_freq: f32,
+) -> f32 {
+    if freq < low_freq || freq > high_freq {
+        return freq;
+    }
+    let scale = 1.0 / warp_alpha;
+    let low_cutoff = vtln_low * warp_alpha.max(1.0);
+    let high_cutoff = vtln_high * warp_alpha.min(1.0);
+    if freq < low_cutoff {
+        let scale_left = (scale * low_cutoff - low_freq) / (low_cutoff - low_freq);
+        low_freq + scale_left * (freq - low_freq)
+    } else if freq < high_cutoff {
+        scale * freq
+    } else {
+        let scale_right = (high_freq - scale * high_cutoff) / (high_freq - high_cutoff);
+        high_freq + scale_right * (freq - high_freq)
+    }
+}
+
+/// `[n_fft][n_bark]` triangular filters spaced evenly on the Bark scale from 0 Hz to Nyquist.
+/// The triangles are built in the Bark domain and left unnormalised.
+pub fn bark_filter_bank(n_fft: usize, n_bark: usize, sr: u32) -> Result<MelFilterBank, MelError> {
+    if n_bark < 1 {
+        return Err(MelError::InvalidConfig("Bark filter bank needs at least one filter".to_string()));
+    }
+    let fft_freqs_bark: Vec<f32> = fft_frequencies(n_fft, sr).into_iter().map(hertz_to_bark).collect();
+    let filter_freqs_bark = linspace(0.0, hertz_to_bark(sr as f32 / 2.0), n_bark + 2);
+
+    create_triangular_filter_bank(&fft_freqs_bark, &filter_freqs_bark)
+}
+
+/// `[n_fft][n_erb]` triangular filters whose edges are spaced evenly on the ERB-rate scale from
+/// `min_freq` to `max_freq`, left unnormalised.
+pub fn erb_filter_bank(n_fft: usize, n_erb: usize, min_freq: f32, max_freq: f32, sr: u32) -> Result<MelFilterBank, MelError> {
+    if n_erb < 1 {
+        return Err(MelError::InvalidConfig("ERB filter bank needs at least one filter".to_string()));
+    }
+    let filter_freqs_hz: Vec<f32> = linspace(hertz_to_erb(min_freq), hertz_to_erb(max_freq), n_erb + 2)
+        .into_iter()
+        .map(erb_to_hertz)
+        .collect();
+
+    create_triangular_filter_bank(&fft_frequencies(n_fft, sr), &filter_freqs_hz)
+}
+
+/// Projects the first `n_fft` bins of each power spectrum frame onto the mel filters, dropping
+/// the last frame as Whisper does when `config.drop_last_frame` is set.
+pub fn apply_mel_filters(power_spec: &[Vec<f32>], mel_filters: &MelFilterBank, config: &MelConfig) -> Result<Vec<Vec<f32>>, MelError> {
+    if mel_filters.n_fft_bins < config.n_fft || mel_filters.n_mels != config.n_mels {
+        return Err(MelError::InvalidConfig(format!(
+            "Filter bank is {}x{}, expected at least {} bins of {} mels",
+            mel_filters.n_fft_bins, mel_filters.n_mels, config.n_fft, config.n_mels
+        )));
+    }
+    let n_frames = if config.drop_last_frame {
+        power_spec.len().saturating_sub(1)
+    } else {
+        power_spec.len()
+    };
+
+    let project_frame = |frame: &Vec<f32>| mel_filters.project(&frame[..config.n_fft]);
+
+    #[cfg(feature = "parallel")]
+    let mel_spectrogram = power_spec[..n_frames].par_iter().map(project_frame).collect();
+    #[cfg(not(feature = "parallel"))]
+    let mel_spectrogram = power_spec[..n_frames].iter().map(project_frame).collect();
+
+    Ok(mel_spectrogram)
+}
+
+/// Logarithm in `base` of every value after clamping it to at least `floor`. A NaN input
+/// survives the clamp and the logarithm and is reported with its position.
+pub fn apply_log(mut mel_spectrogram: Vec<Vec<f32>>, base: LogBase, floor: f32) -> Result<Vec<Vec<f32>>, MelError> {
+    for (frame, row) in mel_spectrogram.iter_mut().enumerate() {
+        for (mel_bin, val) in row.iter_mut().enumerate() {
+            // `f32::max` would replace a NaN with `floor`, so compare explicitly.
+            let floored = if *val < floor { floor } else { *val };
+            *val = match base {
+                LogBase::Log10 => floored.log10(),
+                LogBase::Ln => floored.ln(),
+                LogBase::Log2 => floored.log2(),
+            };
+            if val.is_nan() {
+                return Err(MelError::NanEncountered { frame, mel_bin });
+            }
+        }
+    }
+
+    Ok(mel_spectrogram)
+}
+
+/// Clamps every value to within `drc.floor_db` of the maximum, then maps `x` to
+/// `(x + drc.offset) / drc.scale`.
+pub fn apply_dynamic_range_compression(
+    mut mel_log_spectrogram: Vec<Vec<f32>>,
+    drc: &DrcConfig,
+) -> Result<Vec<Vec<f32>>, MelError> {
+    if mel_log_spectrogram.is_empty() || mel_log_spectrogram[0].is_empty() {
+        return Ok(mel_log_spectrogram);
+    }
+
+    let mut max_val = f32::NEG_INFINITY;
+    for row in mel_log_spectrogram.iter() {
+        for val in row.iter() {
+            if *val > max_val {
+                max_val = *val;
+            }
+        }
+    }
+
+    let floor_val = max_val - drc.floor_db;
+
+    for row in mel_log_spectrogram.iter_mut() {
+        for val in row.iter_mut() {
+            *val = val.max(floor_val);
+            *val = (*val + drc.offset) / drc.scale; 
+        }
+    }
+
+    Ok(mel_log_spectrogram)
+}
+
+/// Per-channel energy normalisation (Wang et al., 2017) of `[n_frames][n_bins]` energies in
+/// place: `(E / (floor + M)^alpha + delta)^root - delta^root`, where `M` is a per-bin
+/// exponential moving average of `E` that starts at the first frame.
+pub fn apply_pcen(power_spec: &mut [Vec<f32>], config: &PcenConfig) {
+    let mut smoothed = power_spec.first().cloned().unwrap_or_default();
+    let offset = config.delta.powf(config.root);
+    for frame in power_spec.iter_mut() {
+        for (energy, m) in frame.iter_mut().zip(smoothed.iter_mut()) {
+            *m = (1.0 - config.smooth) * *m + config.smooth * *energy;
+            let gain = (config.floor + *m).powf(-config.alpha);
+            *energy = (*energy * gain + config.delta).powf(config.root) - offset;
+        }
+    }
+}
+
+pub(crate) fn hertz_to_mel_slaney(frequency: f32) -> f32 {
+    const MIN_LOG_HERTZ: f32 = 1000.0;
+    const MIN_LOG_MEL: f32 = 15.0;
+    let logstep: f32 = 27.0 / (6.4f32.ln());
+
+    let mut mels = 3.0 * frequency / 200.0;
+    if frequency >= MIN_LOG_HERTZ {
+        mels = MIN_LOG_MEL + (frequency / MIN_LOG_HERTZ).ln() * logstep;
+    }
+    mels
+}
+
+pub(crate) fn mel_to_hertz_slaney(mel: f32) -> f32 {
+    const MIN_LOG_HERTZ: f32 = 1000.0;
+    const MIN_LOG_MEL: f32 = 15.0;
+    let logstep: f32 = (6.4f32.ln()) / 27.0;
+
+    let mut f = 200.0 * mel / 3.0;
+    if mel >= MIN_LOG_MEL {
+        f = MIN_LOG_HERTZ * (logstep * (mel - MIN_LOG_MEL)).exp();
+    }
+    f
+}
+
+pub fn hertz_to_mel_htk(f: f32) -> f32 {
+    2595.0 * (1.0 + f / 700.0).log10()
+}
+
+pub fn mel_to_hertz_htk(m: f32) -> f32 {
+    700.0 * (10f32.powf(m / 2595.0) - 1.0)
+}
+
+/// Zwicker's critical-band rate: `13 atan(0.76 f / 1000) + 3.5 atan((f / 7500)^2)`.
+pub fn hertz_to_bark(f: f32) -> f32 {
+    13.0 * (0.76 * f / 1000.0).atan() + 3.5 * (f / 7500.0).powi(2).atan()
+}
+
+/// Glasberg and Moore's ERB-rate: `21.4 log10(1 + 0.00437 f)`.
+pub fn hertz_to_erb(f: f32) -> f32 {
+    21.4 * (1.0 + 0.00437 * f).log10()
+}
+
+pub fn erb_to_hertz(erb: f32) -> f32 {
+    (10f32.powf(erb / 21.4) - 1.0) / 0.00437
+}
+
+/// `num` evenly spaced values from `start` to `end` inclusive; empty for 0 and `[start]` for 1.
+pub(crate) fn linspace(start: f32, end: f32, num: usize) -> Vec<f32> {
+    if num == 0 {
+        return Vec::new();
+    }
+    if num == 1 {
+        return vec![start];
+    }
+    let step = (end - start) / (num - 1) as f32;
+    (0..num).map(|i| start + step * i as f32).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use realfft::RealFftPlanner;
+
+    #[test]
+    fn linspace_of_zero_points_is_empty() {
+        assert!(linspace(0.0, 1.0, 0).is_empty());
+    }
+
+    #[test]
+    fn linspace_of_one_point_is_start() {
+        assert_eq!(linspace(5.0, 5.0, 1), vec![5.0]);
+    }
+
+    #[test]
+    fn linspace_includes_both_ends() {
+        assert_eq!(linspace(0.0, 1.0, 5), vec![0.0, 0.25, 0.5, 0.75, 1.0]);
+    }
+
+    #[test]
+    fn frame_signal_drops_trailing_partial_frame() {
+        let config = MelConfig { frame_length: 4, hop_length: 3, ..MelConfig::whisper_default() };
+        let frames = frame_signal((0..11).map(|i| i as f32).collect(), &config).unwrap();
+        assert_eq!(frames, vec![vec![0.0, 1.0, 2.0, 3.0], vec![3.0, 4.0, 5.0, 6.0], vec![6.0, 7.0, 8.0, 9.0]]);
+    }
+
+    #[test]
+    fn apply_window_multiplies_each_frame() {
+        let frames = apply_window(vec![vec![1.0, 2.0, 3.0], vec![-1.0, 0.0, 1.0]], &[0.5, 1.0, 2.0]).unwrap();
+        assert_eq!(frames, vec![vec![0.5, 2.0, 6.0], vec![-0.5, 0.0, 2.0]]);
+    }
+
+    #[test]
+    fn apply_rfft_puts_a_cosine_in_its_bin() {
+        let len = 16;
+        let impulse: Vec<f32> = (0..len).map(|i| if i == 0 { 1.0 } else { 0.0 }).collect();
+        let cosine: Vec<f32> = (0..len).map(|i| (2.0 * core::f32::consts::PI * 3.0 * i as f32 / len as f32).cos()).collect();
+        let spectrum = apply_rfft(vec![impulse, cosine], &MixedRadixFft::new(len)).unwrap();
+
+        assert_eq!(spectrum[0].len(), len / 2 + 1);
+        assert!(spectrum[0].iter().all(|bin| (bin - Complex::new(1.0, 0.0)).norm() < 1e-6));
+        for (k, bin) in spectrum[1].iter().enumerate() {
+            let expected = if k == 3 { len as f32 / 2.0 } else { 0.0 };
+            assert!((bin.re - expected).abs() < 1e-4 && bin.im.abs() < 1e-4, "bin {}: {}", k, bin);
+        }
+    }
+
+    #[test]
+    fn power_spectrogram_is_squared_magnitude() {
+        let power = power_spectrogram(vec![vec![Complex::new(3.0, 4.0), Complex::new(0.0, -2.0)]]).unwrap();
+        assert_eq!(power, vec![vec![25.0, 4.0]]);
+    }
+
+    #[test]
+    fn mel_filter_bank_has_whisper_shape_and_ordered_peaks() {
+        let bank = mel_filter_bank(&MelConfig::whisper_default()).unwrap();
+        assert_eq!((bank.n_fft_bins, bank.n_mels()), (201, 80));
+        assert!(bank.data.iter().all(|&weight| weight >= 0.0));
+
+        let rows: Vec<&[f32]> = bank.rows().collect();
+        let peaks: Vec<usize> = (0..bank.n_mels())
+            .map(|mel| (0..rows.len()).max_by(|&a, &b| rows[a][mel].total_cmp(&rows[b][mel])).unwrap())
+            .collect();
+        assert!(peaks.windows(2).all(|pair| pair[0] <= pair[1]), "{:?}", peaks);
+    }
+
+    #[test]
+    fn apply_mel_filters_projects_bins_and_drops_the_last_frame() {
+        let config = MelConfig::whisper_default();
+        let bank = mel_filter_bank(&config).unwrap();
+        let one_hot = |bin: usize| -> Vec<f32> { (0..config.n_fft).map(|k| if k == bin { 2.0 } else { 0.0 }).collect() };
+
+        let mel = apply_mel_filters(&[one_hot(10), one_hot(50), one_hot(90)], &bank, &config).unwrap();
+        assert_eq!(mel.len(), 2);
+        let rows: Vec<&[f32]> = bank.rows().collect();
+        for (frame, bin) in mel.iter().zip([10, 50]) {
+            let expected: Vec<f32> = rows[bin].iter().map(|weight| 2.0 * weight).collect();
+            assert_eq!(frame, &expected);
+        }
+
+        let other = MelConfig { n_mels: 40, ..config.clone() };
+        assert!(matches!(apply_mel_filters(&[one_hot(10)], &bank, &other), Err(MelError::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn apply_log_floors_before_taking_the_log() {
+        let log = apply_log(vec![vec![100.0, 0.0]], LogBase::Log10, 1e-10).unwrap();
+        assert_eq!(log, vec![vec![2.0, -10.0]]);
+        assert!(matches!(
+            apply_log(vec![vec![1.0, f32::NAN]], LogBase::Ln, 1e-10),
+            Err(MelError::NanEncountered { frame: 0, mel_bin: 1 })
+        ));
+    }
+
+    #[test]
+    fn dynamic_range_compression_clamps_below_the_peak() {
+        let compressed = apply_dynamic_range_compression(vec![vec![0.0, -4.0, -12.0]], &DrcConfig::whisper()).unwrap();
+        assert_eq!(compressed, vec![vec![1.0, 0.0, -1.0]]);
+    }
+
+    #[test]
+    fn mel_filter_bank_rejects_zero_mels() {
+        let config = MelConfig { n_mels: 0, ..MelConfig::whisper_default() };
+        assert!(matches!(mel_filter_bank(&config), Err(MelError::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn mixed_radix_fft_matches_realfft() {
+        for len in [1, 2, 12, 400, 512, 97] {
+            let frame: Vec<f32> = (0..len).map(|i| ((i * 7 % 13) as f32 - 6.0) / 6.0).collect();
+            let expected = apply_rfft(vec![frame.clone()], RealFftPlanner::<f32>::new().plan_fft_forward(len).as_ref()).unwrap();
+            let actual = apply_rfft(vec![frame], &MixedRadixFft::new(len)).unwrap();
+            for (a, e) in actual[0].iter().zip(&expected[0]) {
+                assert!((a - e).norm() < 1e-4 * len as f32, "length {}: {} vs {}", len, a, e);
+            }
+        }
+    }
+
+    #[test]
+    fn vtln_warp_of_one_is_identity() {
+        let config = MelConfig::whisper_default();
+        let bank = mel_filter_bank(&config).unwrap();
+        let warped = vtln_warp_mel_filterbank(&bank, 1.0, &config).unwrap();
+        assert_eq!((warped.n_fft_bins, warped.n_mels), (bank.n_fft_bins, bank.n_mels));
+        for (w, b) in warped.data.iter().zip(&bank.data) {
+            assert!((w - b).abs() <= 1e-6 * b.abs().max(1.0), "{} vs {}", w, b);
+        }
+    }
+
+    #[test]
+    fn vtln_warp_rejects_invalid_factors() {
+        let config = MelConfig::whisper_default();
+        let bank = mel_filter_bank(&config).unwrap();
+        for warp_alpha in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            assert!(matches!(vtln_warp_mel_filterbank(&bank, warp_alpha, &config), Err(MelError::InvalidConfig(_))));
+        }
+    }
+
+    #[test]
+    fn vtln_warp_needs_inflection_points_inside_the_band() {
+        // The upper inflection point sits 500 Hz below Nyquist (7500 Hz), not below max_freq.
+        for config in [
+            MelConfig { min_freq: 100.0, ..MelConfig::whisper_default() },
+            MelConfig { max_freq: 7400.0, ..MelConfig::whisper_default() },
+        ] {
+            let bank = mel_filter_bank(&config).unwrap();
+            assert!(matches!(vtln_warp_mel_filterbank(&bank, 1.1, &config), Err(MelError::InvalidConfig(_))));
+        }
+        let config = MelConfig { min_freq: 20.0, max_freq: 7600.0, ..MelConfig::whisper_default() };
+        let bank = mel_filter_bank(&config).unwrap();
+        assert!(vtln_warp_mel_filterbank(&bank, 1.1, &config).is_ok());
+    }
+
+    #[test]
+    fn window_coefficients_match_their_definitions() {
+        assert!(make_window(400, WindowType::Rectangular).iter().all(|&w| w == 1.0));
+
+        let hann_sum: f32 = make_window(400, WindowType::Hann).iter().sum();
+        assert!((hann_sum - 200.0).abs() < 1e-3, "Hann sums to {}", hann_sum);
+
+        // Periodic, so the coefficient matching w[0] is the implicit w[len]; check the
+        // first one and the symmetry that places the last one at 0.08 too.
+        let hamming = make_window(400, WindowType::Hamming);
+        assert!((hamming[0] - 0.08).abs() < 1e-6, "Hamming starts at {}", hamming[0]);
+        for i in 1..400 {
+            assert!((hamming[i] - hamming[400 - i]).abs() < 1e-5, "asymmetric at {}", i);
+        }
+    }
+
+    #[test]
+    fn erb_filter_bandwidth_near_1_khz_matches_the_erb_scale() {
+        // Glasberg and Moore's ERB at the filter's centre, 24.7 (4.37 f / 1000 + 1).
+        let theoretical_erb = |f: f32| 24.7 * (4.37 * f / 1000.0 + 1.0);
+        let (n_fft, sr) = (1025, 16000);
+        let bin_hz = sr as f32 / 2.0 / (n_fft - 1) as f32;
+        let fft_freqs = fft_frequencies(n_fft, sr);
+        // Peak-normalised triangles, so the area is the equivalent rectangular bandwidth.
+        let filter = |bank: &MelFilterBank, m: usize| -> (f32, f32) {
+            let weights: Vec<f32> = bank.rows().map(|row| row[m]).collect();
+            let centre = weights.iter().zip(&fft_freqs).map(|(w, f)| w * f).sum::<f32>() / weights.iter().sum::<f32>();
+            (centre, weights.iter().sum::<f32>() * bin_hz)
+        };
+
+        // 44 filters over 0-8 kHz put filter 20 at about 1 kHz, 0.74 ERB apart.
+        let bank = erb_filter_bank(n_fft, 44, 0.0, 8000.0, sr).unwrap();
+        let spacing = hertz_to_erb(8000.0) / 45.0;
+        let (centre, bandwidth) = filter(&bank, 20);
+        assert!((centre - 1000.0).abs() < 20.0, "filter 20 centred at {} Hz", centre);
+        let expected = spacing * theoretical_erb(centre);
+        assert!((bandwidth - expected).abs() < 0.05 * expected, "bandwidth {} Hz, expected {} Hz", bandwidth, expected);
+
+        // With one-ERB spacing, the filter nearest 1 kHz is one ERB (~130 Hz) wide.
+        let n_erb = (hertz_to_erb(8000.0) - 1.0).round() as usize;
+        let bank = erb_filter_bank(n_fft, n_erb, 0.0, 8000.0, sr).unwrap();
+        let (centre, bandwidth) = (0..n_erb)
+            .map(|m| filter(&bank, m))
+            .min_by(|a, b| (a.0 - 1000.0).abs().total_cmp(&(b.0 - 1000.0).abs()))
+            .unwrap();
+        assert!((centre - 1000.0).abs() < 100.0, "nearest filter centred at {} Hz", centre);
+        let expected = theoretical_erb(centre);
+        assert!((bandwidth - expected).abs() < 0.1 * expected, "bandwidth {} Hz, expected {} Hz", bandwidth, expected);
+    }
+
+    #[test]
+    fn slaney_normalised_filters_integrate_to_one() {
+        // A fine grid, so even the narrowest low-frequency triangles span dozens of bins.
+        let (n_fft, sr) = (4097, 16000);
+        let bin_hz = sr as f32 / 2.0 / (n_fft - 1) as f32;
+        let (bank, norms) = mel_filter_bank_with_norms(n_fft, 80, 0.0, 8000.0, sr).unwrap();
+        assert_eq!(norms.len(), 80);
+        for m in 0..80 {
+            let area: f32 = bank.rows().map(|row| row[m]).sum::<f32>() * bin_hz;
+            assert!((area - 1.0).abs() < 0.01, "filter {} integrates to {}", m, area);
+        }
+    }
+
+    #[test]
+    fn overlapping_filters_conserve_energy() {
+        // Band edges leave room for doubled filters inside 0-8 kHz, so none are clipped.
+        let config = MelConfig { n_fft: 4097, min_freq: 100.0, max_freq: 7000.0, ..MelConfig::whisper_default() };
+        let bin_hz = 8000.0 / 4096.0;
+        for filter_overlap in [0.0, 0.5, 1.0] {
+            let bank = mel_filter_bank(&MelConfig { filter_overlap, ..config.clone() }).unwrap();
+            let total_area: f32 = bank.data.iter().sum::<f32>() * bin_hz;
+            assert!((total_area - 80.0).abs() < 0.1, "overlap {}: filters integrate to {}", filter_overlap, total_area);
+        }
+
+        // Unnormalised, the standard triangles sum to one between the first and last centres.
+        let bank = mel_filter_bank(&MelConfig { use_slaney_norm: false, ..config.clone() }).unwrap();
+        let fft_freqs = fft_frequencies(4097, 16000);
+        let edges = mel_filter_edges(&config).unwrap();
+        let (first_centre, last_centre) = (edges[0][1], edges[79][1]);
+        for (row, &freq) in bank.rows().zip(&fft_freqs) {
+            if freq >= first_centre && freq <= last_centre {
+                let sum: f32 = row.iter().sum();
+                assert!((sum - 1.0).abs() < 1e-4, "filters sum to {} at {} Hz", sum, freq);
+            }
+        }
+    }
+
+    #[test]
+    fn frame_signal_views_match_frame_signal_bit_for_bit() {
+        let config = MelConfig::whisper_default();
+        // Odd length, so the trailing partial frame is dropped by both.
+        let samples: Vec<f32> = (0..4321).map(|i| (i as f32 * 0.37).sin() * 1e-3 + i as f32).collect();
+        let owned = frame_signal(samples.clone(), &config).unwrap();
+        let views: Vec<&[f32]> = frame_signal_views(&samples, config.frame_length, config.hop_length).collect();
+
+        assert_eq!(owned.len(), views.len());
+        for (frame, view) in owned.iter().zip(&views) {
+            let bits = |values: &[f32]| values.iter().map(|v| v.to_bits()).collect::<Vec<_>>();
+            assert_eq!(bits(frame), bits(view));
+        }
+    }
+}
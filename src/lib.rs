@@ -1,20 +1,88 @@
-use hound::WavReader;
-use std::i16;
-use std::ffi::CStr;
-use std::slice;
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+
+/// Compiles the enclosed items only with the `std` feature. Without it the crate is the
+/// allocation-only [`dsp`] core and the configuration types it takes.
+macro_rules! std_only {
+    ($($item:item)*) => {
+        $(
+            #[cfg(feature = "std")]
+            $item
+        )*
+    };
+}
+
+#[cfg(not(feature = "std"))]
+use alloc::{format, string::{String, ToString}, vec::Vec};
+use core::error::Error;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+use core::fmt;
+use dsp::{hertz_to_mel_slaney, mel_to_hertz_slaney};
+pub use dsp::{erb_to_hertz, fft_frequencies, hertz_to_bark, hertz_to_erb, hertz_to_mel_htk, make_window, mel_to_hertz_htk};
+
+std_only! {
+use hound::{SampleFormat, WavReader};
+use std::borrow::Cow;
+use std::ffi::c_void;
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read, Write};
+use std::sync::Arc;
 use std::ptr;
+#[cfg(not(feature = "wasm"))]
+use hound::{WavSpec, WavWriter};
+#[cfg(not(feature = "wasm"))]
+use std::ffi::CStr;
+#[cfg(not(feature = "wasm"))]
 use std::os::raw::c_char;
-use rubato::{SincFixedIn, SincInterpolationType, SincInterpolationParameters, WindowFunction, Resampler};
-use realfft::RealFftPlanner;
+#[cfg(not(feature = "wasm"))]
+use std::io::BufWriter;
+#[cfg(not(feature = "wasm"))]
+use std::slice;
+use rubato::{calculate_cutoff, SincFixedIn, SincFixedOut, SincInterpolationType, SincInterpolationParameters, WindowFunction, Resampler};
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
 use num_complex::Complex;
+use dsp::{
+    apply_dynamic_range_compression, apply_log, apply_mel_filters, apply_pcen, apply_rfft, apply_window, bark_filter_bank,
+    erb_filter_bank, frame_signal, frame_signal_views, magnitude_spectrogram, mel_filter_bank, power_spectrogram,
+    MelFilterBank,
+};
+}
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+#[cfg(feature = "augment")]
+use rand::{rngs::StdRng, SeedableRng};
+
+#[cfg(feature = "augment")]
+pub mod augment;
+pub mod dsp;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "wasm")]
+mod wasm;
 
+std_only! {
+/// A mel-major `n_mels` x `n_frames` buffer owned by this library. C callers must pass it to
+/// [`free_spectrogram_data`] exactly once. In Rust it frees itself when dropped, so it must
+/// not also be passed to the free function after a copy of its fields has been made.
 #[repr(C)]
+#[must_use = "dropping MelSpectrogramData frees its buffer"]
 pub struct MelSpectrogramData {
     pub data: *mut f32,
     pub n_frames: usize,
     pub n_mels: usize,
 }
 
+// C code never runs this; it releases the buffer when `free_spectrogram_data` drops its argument.
+impl Drop for MelSpectrogramData {
+    fn drop(&mut self) {
+        // SAFETY: `data` is null or a boxed slice of `n_frames * n_mels` values that this value owns.
+        unsafe { free_raw_buffer(self.data, self.n_frames * self.n_mels) };
+    }
+}
+
 impl Default for MelSpectrogramData {
     fn default() -> Self {
         MelSpectrogramData { 
@@ -25,425 +93,5128 @@ impl Default for MelSpectrogramData {
     }
 }
 
-fn read_wav(path: &str) -> Result<(Vec<f32>, u32), String> {
-    let reader = WavReader::open(path)
-        .map_err(|e| format!("Failed to open WAV: {}", e))?;
-    let spec = reader.spec();
-    let orig_sample_rate = spec.sample_rate;
-    let samples: Vec<f32> = reader
-        .into_samples::<i16>()
-        .filter_map(Result::ok)
-        .map(|s| s as f32 / i16::MAX as f32)
-        .collect();
+impl TryFrom<MelSpectrogram> for MelSpectrogramData {
+    type Error = MelError;
 
-    let mono = if spec.channels == 1 {
-        samples
-    } else {
-        samples
-            .chunks(spec.channels as usize)
-            .map(|chunk| chunk.iter().sum::<f32>() / chunk.len() as f32)
-            .collect()
-    };
+    fn try_from(spectrogram: MelSpectrogram) -> Result<Self, MelError> {
+        let n_frames = spectrogram.n_frames;
+        let mut n_mels = spectrogram.n_mels;
 
-    Ok((mono, orig_sample_rate))
-}
+        let frames: Vec<Vec<f32>> = spectrogram.data
+            .chunks(n_mels)
+            .map(|frame| frame.to_vec())
+            .collect();
+        let mut flat_spectrogram: Vec<f32> = transpose(frames)?.into_iter().flatten().collect();
+        if let Some(log_energy) = spectrogram.log_energy {
+            flat_spectrogram.extend(log_energy);
+            n_mels += 1;
+        }
 
-fn resample_audio(samples: Vec<f32>, orig_rate: u32, target_rate: u32) -> Result<Vec<f32>, String> {
-    if orig_rate == target_rate {
-        return Ok(samples)
+        let (data, _) = into_raw_buffer(flat_spectrogram);
+
+        Ok(MelSpectrogramData {
+            data,
+            n_frames,
+            n_mels,
+        })
     }
+}
 
-    let ratio = target_rate as f64 / orig_rate as f64;
-    let params = SincInterpolationParameters {
-        sinc_len: 256,
-        f_cutoff: 0.95,
-        oversampling_factor: 256,
-        interpolation: SincInterpolationType::Cubic,
-        window: WindowFunction::BlackmanHarris2,
-    };
-    let mut resampler = SincFixedIn::<f32>::new(ratio, 1.0, params, samples.len(), 1)
-        .map_err(|e| format!("Resampler init error: {:?}", e))?;
-    let outputs = resampler
-        .process(&[samples], None)
-        .map_err(|e| format!("Resampler init error: {:?}", e))?;
-    Ok(outputs.into_iter().next().unwrap())
+/// Mel spectrogram plus per-frame auxiliary tracks for the C API. Each track pointer is null
+/// when the track was not computed.
+#[repr(C)]
+pub struct ExtendedMelSpectrogramData {
+    pub mel: MelSpectrogramData,
+    pub zcr: *mut f32,
+    pub n_zcr: usize,
+    pub spectral_centroid: *mut f32,
+    pub n_spectral_centroid: usize,
+    pub spectral_flux: *mut f32,
+    pub n_spectral_flux: usize,
 }
 
-fn pad_or_truncate(mut samples: Vec<f32>, target_len: usize, frame_length: usize) -> Result<Vec<f32>, String> {
-    if samples.len() > target_len {
-        samples.truncate(target_len);
-    } else if samples.len() < target_len {
-        samples.resize(target_len, 0.0);
+impl Default for ExtendedMelSpectrogramData {
+    fn default() -> Self {
+        ExtendedMelSpectrogramData {
+            mel: MelSpectrogramData::default(),
+            zcr: ptr::null_mut(),
+            n_zcr: 0,
+            spectral_centroid: ptr::null_mut(),
+            n_spectral_centroid: 0,
+            spectral_flux: ptr::null_mut(),
+            n_spectral_flux: 0,
+        }
     }
+}
 
-    let pad_each = frame_length / 2;
-    let mut out = Vec::with_capacity(pad_each + samples.len() + pad_each);
-    out.extend(std::iter::repeat(0.0).take(pad_each));
-    out.extend(samples);
-    out.extend(std::iter::repeat(0.0).take(pad_each));
-    Ok(out)
+impl TryFrom<MelSpectrogram> for ExtendedMelSpectrogramData {
+    type Error = MelError;
+
+    fn try_from(mut spectrogram: MelSpectrogram) -> Result<Self, MelError> {
+        let zcr = spectrogram.zero_crossing_rate.take();
+        let spectral_centroid = spectrogram.spectral_centroid.take();
+        let spectral_flux = spectrogram.spectral_flux.take();
+        let mel = MelSpectrogramData::try_from(spectrogram)?;
+        let (zcr, n_zcr) = zcr.map_or((ptr::null_mut(), 0), into_raw_buffer);
+        let (spectral_centroid, n_spectral_centroid) =
+            spectral_centroid.map_or((ptr::null_mut(), 0), into_raw_buffer);
+        let (spectral_flux, n_spectral_flux) = spectral_flux.map_or((ptr::null_mut(), 0), into_raw_buffer);
+
+        Ok(ExtendedMelSpectrogramData {
+            mel,
+            zcr,
+            n_zcr,
+            spectral_centroid,
+            n_spectral_centroid,
+            spectral_flux,
+            n_spectral_flux,
+        })
+    }
 }
 
-fn frame_signal(samples: Vec<f32>, frame_length: usize, hop_length: usize) -> Result<Vec<Vec<f32>>, String> {
-    let num_frames = (samples.len() - frame_length + hop_length) / hop_length;
-    let mut frames = Vec::with_capacity(num_frames);
+/// Scalar extraction parameters for `extract_mel_spectrogram_configured`. Fields not listed keep
+/// their Whisper defaults. C layout:
+///
+/// ```c
+/// typedef struct {
+///     uint32_t sample_rate;
+///     size_t n_mels;
+///     size_t n_fft;          /* frame_length / 2 + 1 */
+///     size_t hop_length;
+///     size_t frame_length;
+///     float min_freq;
+///     float max_freq;
+///     uint8_t use_slaney_norm; /* 0 = false, anything else = true */
+/// } CMelConfig;
+/// ```
+///
+/// This layout is frozen; later additions go into versioned structs such as [`CMelConfigV2`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct CMelConfig {
+    pub sample_rate: u32,
+    pub n_mels: usize,
+    pub n_fft: usize,
+    pub hop_length: usize,
+    pub frame_length: usize,
+    pub min_freq: f32,
+    pub max_freq: f32,
+    pub use_slaney_norm: u8,
+}
 
-    for i in 0..num_frames {
-        let start = i * hop_length;
-        let end = start + frame_length;
+/// [`CMelConfig`] plus a progress callback, for the `_v2` entry points. C layout:
+///
+/// ```c
+/// typedef struct {
+///     CMelConfig config;
+///     /* Optional; called with the completed fraction in [0, 1] and progress_user_data. */
+///     void (*progress)(float fraction, void *user_data);
+///     void *progress_user_data;
+/// } CMelConfigV2;
+/// ```
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct CMelConfigV2 {
+    pub config: CMelConfig,
+    pub progress: Option<extern "C" fn(fraction: f32, user_data: *mut c_void)>,
+    pub progress_user_data: *mut c_void,
+}
 
-        if end <= samples.len() {
-            frames.push(samples[start..end].to_vec());
+#[cfg(not(feature = "wasm"))]
+impl CMelConfigV2 {
+    /// Forwards to the C progress callback, if any.
+    fn report_progress(&self, fraction: f32) {
+        if let Some(callback) = self.progress {
+            callback(fraction, self.progress_user_data);
         }
     }
-
-    Ok(frames)
 }
 
-fn apply_hann_window(mut frames: Vec<Vec<f32>>) -> Result<Vec<Vec<f32>>, String> {
-    let frame_len = frames[0].len();
-    let hann: Vec<f32> = (0..frame_len)
-        .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / frame_len as f32).cos())
-        .collect();
-    
-    for frame in frames.iter_mut() {
-        for (i, sample) in frame.iter_mut().enumerate() {
-            *sample *= hann[i]
+impl From<&CMelConfig> for MelConfig {
+    fn from(config: &CMelConfig) -> Self {
+        MelConfig {
+            sample_rate: config.sample_rate,
+            n_mels: config.n_mels,
+            n_fft: config.n_fft,
+            hop_length: config.hop_length,
+            frame_length: config.frame_length,
+            min_freq: config.min_freq,
+            max_freq: config.max_freq,
+            use_slaney_norm: config.use_slaney_norm != 0,
+            ..MelConfig::whisper_default()
         }
     }
+}
 
-    Ok(frames)
+/// Hands ownership of `buffer` to the caller as a pointer/length pair, to be released with
+/// `free_raw_buffer`.
+fn into_raw_buffer(buffer: Vec<f32>) -> (*mut f32, usize) {
+    let len = buffer.len();
+    (Box::into_raw(buffer.into_boxed_slice()).cast::<f32>(), len)
 }
 
-fn apply_rfft(frames: Vec<Vec<f32>>) -> Result<Vec<Vec<Complex<f32>>>, String> {
-    let frame_len = frames[0].len();
-    
-    let mut planner = RealFftPlanner::<f32>::new();
-    let r2c = planner.plan_fft_forward(frame_len);
+/// Reconstructs and drops the `Box<[f32]>` created by `into_raw_buffer`.
+unsafe fn free_raw_buffer(data: *mut f32, len: usize) {
+    if !data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(data, len)));
+    }
+}
 
-    let mut output = r2c.make_output_vec();
-    let mut spectrogram = Vec::with_capacity(frames.len());
+}
 
-    for frame in frames {
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum MelError {
+    #[cfg(feature = "std")]
+    IoError(std::io::Error),
+    #[cfg(feature = "std")]
+    WavError(hound::Error),
+    #[cfg(feature = "std")]
+    ResamplerInit(rubato::ResamplerConstructionError),
+    #[cfg(feature = "std")]
+    ResamplerProcess(rubato::ResampleError),
+    #[cfg(feature = "std")]
+    FftError(realfft::FftError),
+    InvalidConfig(String),
+    InvalidAudio(String),
+    UnsupportedFormat(String),
+    /// A NaN reached the log stage at this frame and mel bin.
+    NanEncountered { frame: usize, mel_bin: usize },
+}
 
-        let mut input = r2c.make_input_vec();
-        input.copy_from_slice(&frame);
+impl fmt::Display for MelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            MelError::IoError(e) => write!(f, "I/O error: {}", e),
+            #[cfg(feature = "std")]
+            MelError::WavError(e) => write!(f, "Failed to read WAV: {}", e),
+            #[cfg(feature = "std")]
+            MelError::ResamplerInit(e) => write!(f, "Resampler init error: {}", e),
+            #[cfg(feature = "std")]
+            MelError::ResamplerProcess(e) => write!(f, "Resampler error: {}", e),
+            #[cfg(feature = "std")]
+            MelError::FftError(e) => write!(f, "FFT error: {}", e),
+            MelError::InvalidConfig(msg) => write!(f, "Invalid config: {}", msg),
+            MelError::InvalidAudio(msg) => write!(f, "Invalid audio: {}", msg),
+            MelError::UnsupportedFormat(msg) => write!(f, "Unsupported format: {}", msg),
+            MelError::NanEncountered { frame, mel_bin } => {
+                write!(f, "Encountered NaN in mel spectrogram at frame {}, mel bin {}", frame, mel_bin)
+            }
+        }
+    }
+}
 
-        r2c.process(&mut input, &mut output)
-            .map_err(|e| format!("FFT error: {:?}", e))?;
+impl Error for MelError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            MelError::IoError(e) => Some(e),
+            #[cfg(feature = "std")]
+            MelError::WavError(e) => Some(e),
+            #[cfg(feature = "std")]
+            MelError::ResamplerInit(e) => Some(e),
+            #[cfg(feature = "std")]
+            MelError::ResamplerProcess(e) => Some(e),
+            #[cfg(feature = "std")]
+            MelError::FftError(e) => Some(e),
+            MelError::InvalidConfig(_)
+            | MelError::InvalidAudio(_)
+            | MelError::UnsupportedFormat(_)
+            | MelError::NanEncountered { .. } => None,
+        }
+    }
+}
 
-        spectrogram.push(output.clone());
+std_only! {
+impl From<std::io::Error> for MelError {
+    fn from(e: std::io::Error) -> Self {
+        MelError::IoError(e)
     }
+}
 
-    Ok(spectrogram)
+impl From<hound::Error> for MelError {
+    fn from(e: hound::Error) -> Self {
+        match e {
+            hound::Error::IoError(io) => MelError::IoError(io),
+            other => MelError::WavError(other),
+        }
+    }
 }
 
-fn power_spectrogram(spectrogram: Vec<Vec<Complex<f32>>>) -> Result<Vec<Vec<f32>>, String> {
-    Ok(spectrogram.into_iter()
-        .map(|frame|
-            frame.into_iter()
-                .map(|c| c.norm_sqr())
-                .collect()
-        ).collect()
-    )
+impl From<rubato::ResamplerConstructionError> for MelError {
+    fn from(e: rubato::ResamplerConstructionError) -> Self {
+        MelError::ResamplerInit(e)
+    }
+}
+
+impl From<rubato::ResampleError> for MelError {
+    fn from(e: rubato::ResampleError) -> Self {
+        MelError::ResamplerProcess(e)
+    }
 }
 
-fn hertz_to_mel_slaney(frequency: f32) -> f32 {
-    const MIN_LOG_HERTZ: f32 = 1000.0;
-    const MIN_LOG_MEL: f32 = 15.0;
-    let logstep: f32 = 27.0 / (6.4f32.ln());
+impl From<realfft::FftError> for MelError {
+    fn from(e: realfft::FftError) -> Self {
+        MelError::FftError(e)
+    }
+}
+}
 
-    let mut mels = 3.0 * frequency / 200.0;
-    if frequency >= MIN_LOG_HERTZ {
-        mels = MIN_LOG_MEL + (frequency / MIN_LOG_HERTZ).ln() * logstep;
+#[cfg(feature = "flac")]
+impl From<claxon::Error> for MelError {
+    fn from(e: claxon::Error) -> Self {
+        match e {
+            claxon::Error::IoError(io) => MelError::IoError(io),
+            claxon::Error::FormatError(msg) => MelError::InvalidAudio(format!("Malformed FLAC: {}", msg)),
+            claxon::Error::Unsupported(msg) => MelError::UnsupportedFormat(format!("FLAC: {}", msg)),
+        }
     }
-    mels
 }
 
-fn mel_to_hertz_slaney(mel: f32) -> f32 {
-    const MIN_LOG_HERTZ: f32 = 1000.0;
-    const MIN_LOG_MEL: f32 = 15.0;
-    let logstep: f32 = (6.4f32.ln()) / 27.0;
+#[cfg(feature = "hdf5")]
+impl From<hdf5::Error> for MelError {
+    fn from(e: hdf5::Error) -> Self {
+        MelError::IoError(std::io::Error::other(format!("HDF5: {}", e)))
+    }
+}
 
-    let mut f = 200.0 * mel / 3.0;
-    if mel >= MIN_LOG_MEL {
-        f = MIN_LOG_HERTZ * (logstep * (mel - MIN_LOG_MEL)).exp();
+#[cfg(feature = "ogg")]
+impl From<lewton::VorbisError> for MelError {
+    fn from(e: lewton::VorbisError) -> Self {
+        match e {
+            lewton::VorbisError::OggError(lewton::OggReadError::ReadError(io)) => MelError::IoError(io),
+            other => MelError::InvalidAudio(format!("Malformed Ogg Vorbis: {}", other)),
+        }
     }
-    f
 }
 
-fn linspace(start: f32, end: f32, num: usize) -> Vec<f32> {
-    if num == 0 {
-        return Vec::new();
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MelScale {
+    /// Slaney's piecewise linear/log scale, as used by Whisper and librosa.
+    #[default]
+    Slaney,
+    /// `2595 * log10(1 + f / 700)`, as used by HTK and Kaldi.
+    Htk,
+}
+
+impl MelScale {
+    fn hertz_to_mel(self, frequency: f32) -> f32 {
+        match self {
+            MelScale::Slaney => hertz_to_mel_slaney(frequency),
+            MelScale::Htk => hertz_to_mel_htk(frequency),
+        }
     }
-    if num == 1 {
-        return vec![start];
+
+    fn mel_to_hertz(self, mel: f32) -> f32 {
+        match self {
+            MelScale::Slaney => mel_to_hertz_slaney(mel),
+            MelScale::Htk => mel_to_hertz_htk(mel),
+        }
     }
-    let step = (end - start) / (num - 1) as f32;
-    (0..num).map(|i| start + step * i as f32).collect()
 }
 
-fn create_triangular_filter_bank(fft_freqs: &[f32], filter_freqs: &[f32]) -> Result<Vec<Vec<f32>>, String> {
-    let num_frequency_bins = fft_freqs.len();
-    let num_mel_filters = filter_freqs.len() - 2;
+/// The auditory scale the spectrogram's filter bank is spaced on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FilterBankType {
+    /// [`mel_filter_bank`](dsp::mel_filter_bank), shaped by `mel_scale`, `min_freq`, `max_freq`
+    /// and `use_slaney_norm`.
+    #[default]
+    Mel,
+    /// [`bark_filter_bank`](dsp::bark_filter_bank) of `n_mels` filters up to Nyquist.
+    Bark,
+    /// [`erb_filter_bank`](dsp::erb_filter_bank) of `n_mels` filters from `min_freq` to `max_freq`.
+    Erb,
+}
+
+/// Speed/quality trade-off of the sinc resampler used when the input's rate differs from
+/// `sample_rate`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResamplingQuality {
+    /// 64-tap sinc, 128x oversampling, linear interpolation.
+    Fast,
+    /// 256-tap sinc, 256x oversampling, cubic interpolation.
+    #[default]
+    Medium,
+    /// 512-tap sinc, 512x oversampling, cubic interpolation.
+    Best,
+}
+
+/// How multi-channel audio is reduced to mono.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum DownmixStrategy {
+    /// Equal-weight mean of all channels.
+    #[default]
+    Average,
+    /// Keep only the given zero-based channel.
+    SelectChannel(u16),
+    /// Weighted sum with one weight per channel.
+    WeightedSum(Vec<f32>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WindowType {
+    #[default]
+    Hann,
+    Hamming,
+    Blackman,
+    BlackmanHarris,
+    Rectangular,
+    Flattop,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogBase {
+    #[default]
+    Log10,
+    Ln,
+    Log2,
+}
+
+/// The spectrum fed to the mel filter bank.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpectrogramType {
+    /// `|X|^2`, as Whisper uses.
+    #[default]
+    Power,
+    /// `|X|`.
+    Amplitude,
+}
+
+/// The scale of the values produced from the mel filter bank output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpectrogramScale {
+    /// Linear mel energies.
+    Raw,
+    /// Logarithm in `MelConfig::log_base` followed by Whisper's dynamic range compression.
+    #[default]
+    Log10,
+    /// `10 * log10(S)`, see [`power_to_db`].
+    PowerDb,
+    /// `20 * log10(S)`, see [`amplitude_to_db`].
+    AmplitudeDb,
+}
+
+/// How the `frame_length / 2` samples added on each side before framing are filled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PaddingMode {
+    #[default]
+    Zero,
+    /// Mirror the signal around its first and last samples (numpy's `reflect`).
+    Reflect,
+    /// Continue circularly from the opposite end.
+    Wrap,
+    /// Repeat the first and last samples.
+    Edge,
+}
+
+/// Waveform normalisation applied after padding, just before framing, or PCEN of the mel
+/// energies.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum NormalizationMode {
+    #[default]
+    None,
+    /// Zero mean, unit variance.
+    MeanVariance,
+    /// Largest absolute sample scaled to 1.
+    Peak,
+    /// Unit root-mean-square level.
+    Rms,
+    /// Leaves the waveform alone and applies [`apply_pcen`](dsp::apply_pcen) to the mel energies
+    /// in place of `output_scale` and `drc`.
+    Pcen(PcenConfig),
+}
+
+/// Per-bin cepstral mean and variance normalisation applied to the feature matrix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CmvnMode {
+    #[default]
+    None,
+    /// Statistics over the whole utterance.
+    Global,
+    /// Statistics over a window of `width` frames centred on each frame.
+    SlidingWindow { width: usize },
+}
+
+/// Scaling of the final feature vectors onto the unit hypersphere.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PostNormalizeMode {
+    #[default]
+    None,
+    /// Every frame to unit L2 norm, see [`l2_normalize_frames`].
+    Frame,
+    /// The whole matrix to unit L2 norm, see [`l2_normalize_global`].
+    Global,
+}
+
+/// Energy-threshold voice activity detection run on the resampled signal, see [`apply_vad`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct VadConfig {
+    /// Length of the non-overlapping energy windows in samples; 160 is 10 ms at 16 kHz.
+    pub window_length: usize,
+    /// Windows whose mean energy is below this level (dBFS) are silent.
+    pub energy_threshold_db: f32,
+    /// Silent windows kept after each speech segment so trailing phonemes are not clipped.
+    pub hangover_frames: usize,
+    /// Speech segments shorter than this many windows are treated as silence.
+    pub min_speech_frames: usize,
+}
 
-    if num_mel_filters <= 0 {
-        return Err("Number of mel filters must be positive.".to_string());
+impl Default for VadConfig {
+    fn default() -> Self {
+        VadConfig {
+            window_length: 160,
+            energy_threshold_db: -40.0,
+            hangover_frames: 5,
+            min_speech_frames: 3,
+        }
     }
+}
 
-    let filter_diffs: Vec<f32> = filter_freqs.windows(2)
-        .map(|pair| pair[1] - pair[0])
-        .collect();
+/// White Gaussian noise mixed into the mono signal before any other processing, see
+/// [`augment::add_gaussian_noise`]. Requires the `augment` feature.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoiseAugConfig {
+    /// Signal-to-noise ratio of the result in dB; `f32::INFINITY` adds nothing.
+    pub snr_db: f32,
+    /// Seeds the noise generator, so extraction stays deterministic for a given config.
+    pub seed: u64,
+}
 
-    let mut mel_filters = vec![vec![0.0f32; num_mel_filters]; num_frequency_bins];
+/// Clamps log-mel values to within `floor_db` of the peak, then maps `x` to
+/// `(x + offset) / scale`. Values are in the units of the log output, so Whisper's 8 keeps 80 dB
+/// of `log10` power.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DrcConfig {
+    pub floor_db: f32,
+    pub offset: f32,
+    pub scale: f32,
+}
 
-    for (k, &fft_freq) in fft_freqs.iter().enumerate() {
-        let mut slopes = vec![0.0f32; filter_freqs.len()];
-        for (i, &filter_freq) in filter_freqs.iter().enumerate() {
-            slopes[i] = filter_freq - fft_freq;
+impl DrcConfig {
+    /// Whisper's `log_spec = (max(log_spec, log_spec.max() - 8) + 4) / 4`.
+    pub fn whisper() -> Self {
+        DrcConfig {
+            floor_db: 8.0,
+            offset: 4.0,
+            scale: 4.0,
         }
+    }
+}
 
-        for m in 0..num_mel_filters {
-            let down_slope = if filter_diffs[m] != 0.0 {
-                -slopes[m] / filter_diffs[m]
-            } else {
-                0.0
-            };
-            let up_slope = if filter_diffs[m + 1] != 0.0 {
-                slopes[m + 2] / filter_diffs[m + 1]
-            } else {
-                0.0
-            };
-            mel_filters[k][m] = down_slope.min(up_slope).max(0.0);
+/// Parameters of per-channel energy normalisation, [`apply_pcen`](dsp::apply_pcen).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PcenConfig {
+    /// Exponent of the automatic gain control, between 0 and 1.
+    pub alpha: f32,
+    /// Bias added before root compression.
+    pub delta: f32,
+    /// Root compression exponent, usually 0.5.
+    pub root: f32,
+    /// Weight of the current frame in the smoothing filter, between 0 and 1.
+    pub smooth: f32,
+    /// Added to the smoothed energy to avoid dividing by zero.
+    pub floor: f32,
+}
+
+impl Default for PcenConfig {
+    /// The values of Wang et al. (2017): alpha 0.98, delta 2, root 0.5, smooth 0.025.
+    fn default() -> Self {
+        PcenConfig {
+            alpha: 0.98,
+            delta: 2.0,
+            root: 0.5,
+            smooth: 0.025,
+            floor: 1e-6,
         }
     }
+}
+
+std_only! {
+/// Search range and voicing threshold of the YIN pitch tracker, [`extract_pitch_track`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PitchConfig {
+    /// Lowest pitch searched for, in Hz.
+    pub fmin: f32,
+    /// Highest pitch searched for, in Hz.
+    pub fmax: f32,
+    /// Absolute threshold on the cumulative mean normalised difference; frames whose minimum
+    /// stays above it are unvoiced.
+    pub threshold: f32,
+}
 
-    Ok(mel_filters)
+impl Default for PitchConfig {
+    /// 50 to 500 Hz, as [`estimate_f0`] searches, with the paper's threshold of 0.1.
+    fn default() -> Self {
+        PitchConfig {
+            fmin: MIN_F0,
+            fmax: MAX_F0,
+            threshold: 0.1,
+        }
+    }
+}
 }
 
-fn mel_filter_bank(
-    num_frequency_bins: usize,
-    num_mel_filters: usize,
-    min_frequency: f32,
-    max_frequency: f32,
-    sampling_rate: u32,
-    use_slaney_norm: bool,
-) -> Result<Vec<Vec<f32>>, String> {
-    let nyquist = sampling_rate as f32 / 2.0;
-    let mel_min = hertz_to_mel_slaney(min_frequency);
-    let mel_max = hertz_to_mel_slaney(max_frequency);
+/// One resolution of [`multi_resolution_stft`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StftConfig {
+    /// FFT size and window length in samples; each frame has `n_fft / 2 + 1` bins.
+    pub n_fft: usize,
+    pub hop_length: usize,
+    pub window_type: WindowType,
+}
 
-    let mel_freqs_vec = linspace(mel_min, mel_max, num_mel_filters + 2);
+/// Parameters of the feature extraction pipeline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MelConfig {
+    pub sample_rate: u32,
+    pub resampling_quality: ResamplingQuality,
+    /// Audio is zero-padded or truncated to this many seconds, 30 for Whisper. `None` keeps the
+    /// actual length, so the frame count follows the input.
+    pub max_duration_secs: Option<f32>,
+    pub frame_length: usize,
+    pub hop_length: usize,
+    pub n_mels: usize,
+    pub n_fft: usize,
+    pub min_freq: f32,
+    pub max_freq: f32,
+    pub use_slaney_norm: bool,
+    pub mel_scale: MelScale,
+    /// Widens each mel filter about its fixed centre: 0 gives the standard triangles meeting at
+    /// their neighbours' peaks, 1 doubles every filter's width. Ignored by the Bark and ERB banks.
+    pub filter_overlap: f32,
+    pub filter_bank_type: FilterBankType,
+    pub downmix: DownmixStrategy,
+    /// Adds Gaussian noise to the mono signal. `None` disables it.
+    pub noise_aug: Option<NoiseAugConfig>,
+    pub padding_mode: PaddingMode,
+    /// Whisper discards the last STFT frame; librosa keeps it.
+    pub drop_last_frame: bool,
+    pub window_type: WindowType,
+    pub log_base: LogBase,
+    /// Values are clamped to at least this before taking the logarithm.
+    pub log_floor: f32,
+    pub spectrogram_type: SpectrogramType,
+    pub output_scale: SpectrogramScale,
+    /// Dynamic range compression applied to `SpectrogramScale::Log10` output. `None` returns the
+    /// plain logarithm.
+    pub drc: Option<DrcConfig>,
+    /// Dynamic range kept below the peak by the dB scales. `None` keeps everything.
+    pub top_db: Option<f32>,
+    /// Pre-emphasis coefficient applied before resampling, typically 0.97. `None` disables it.
+    pub preemphasis_coeff: Option<f32>,
+    /// Filters the resampled audio with [`apply_a_weighting`] just before framing. Needs a
+    /// `sample_rate` above 2 kHz.
+    pub a_weighting: bool,
+    /// Shifts the pitch of the resampled audio by this many semitones with [`pitch_shift`].
+    /// `None` disables it.
+    pub pitch_shift_semitones: Option<f32>,
+    /// Speeds the resampled audio up by this factor, after any pitch shift, with
+    /// [`time_stretch`]; 0.9 to 1.1 is typical for speed perturbation. `None` disables it.
+    pub time_stretch_rate: Option<f32>,
+    /// Strips silent windows after resampling. `None` disables it.
+    pub vad: Option<VadConfig>,
+    /// Trims leading and trailing audio quieter than this many dB below the loudest frame, as
+    /// [`trim_silence`] does. `None` disables it.
+    pub trim_top_db: Option<f32>,
+    /// Whisper feeds the raw waveform to its STFT, so its default is `NormalizationMode::None`.
+    pub normalization: NormalizationMode,
+    /// Normalises each bin of the scaled spectrogram before deltas are appended. `None` disables it.
+    pub cmvn: Option<CmvnMode>,
+    /// Computes per-frame log energy; the C API appends it as an extra row after the mel bins.
+    pub include_log_energy: bool,
+    /// Computes per-frame zero-crossing rate, returned by the extended C API as its own array.
+    pub include_zero_crossing_rate: bool,
+    /// Computes the per-frame spectral centroid, returned by the extended C API as its own array.
+    pub include_spectral_centroid: bool,
+    /// Computes the per-frame spectral flux, returned by the extended C API as its own array.
+    pub include_spectral_flux: bool,
+    /// Estimates per-frame pitch between 50 and 500 Hz with [`estimate_f0`].
+    pub extract_f0: bool,
+    /// 0 for static features only, 1 to append deltas, 2 to also append delta-deltas. Each
+    /// order adds another `n_mels` values to every output frame.
+    pub delta_order: u8,
+    /// L2 normalisation applied last, after deltas are appended.
+    pub post_normalize: PostNormalizeMode,
+}
 
-    let mut filter_freqs_hz: Vec<f32> = Vec::with_capacity(mel_freqs_vec.len());
+impl MelConfig {
+    /// The parameters used by OpenAI Whisper: 30 s of 16 kHz audio, 80 mels.
+    pub fn whisper_default() -> Self {
+        MelConfig {
+            sample_rate: 16000,
+            resampling_quality: ResamplingQuality::Medium,
+            max_duration_secs: Some(30.0),
+            frame_length: 400,
+            hop_length: 160,
+            n_mels: 80,
+            n_fft: 201,
+            min_freq: 0.0,
+            max_freq: 8000.0,
+            use_slaney_norm: true,
+            mel_scale: MelScale::Slaney,
+            filter_overlap: 0.0,
+            filter_bank_type: FilterBankType::Mel,
+            downmix: DownmixStrategy::Average,
+            noise_aug: None,
+            padding_mode: PaddingMode::Zero,
+            drop_last_frame: true,
+            window_type: WindowType::Hann,
+            log_base: LogBase::Log10,
+            log_floor: 1e-10,
+            spectrogram_type: SpectrogramType::Power,
+            output_scale: SpectrogramScale::Log10,
+            drc: Some(DrcConfig::whisper()),
+            top_db: Some(80.0),
+            preemphasis_coeff: None,
+            a_weighting: false,
+            pitch_shift_semitones: None,
+            time_stretch_rate: None,
+            vad: None,
+            trim_top_db: None,
+            normalization: NormalizationMode::None,
+            cmvn: None,
+            include_log_energy: false,
+            include_zero_crossing_rate: false,
+            include_spectral_centroid: false,
+            include_spectral_flux: false,
+            extract_f0: false,
+            delta_order: 0,
+            post_normalize: PostNormalizeMode::None,
+        }
+    }
 
-    for mel in mel_freqs_vec.iter() {
-        filter_freqs_hz.push(mel_to_hertz_slaney(*mel));
+    /// The defaults of `librosa.feature.melspectrogram` at `sample_rate`: a 2048-point FFT every
+    /// 512 samples, 128 Slaney-normalised Slaney-scale mels up to Nyquist, and linear power
+    /// output for the whole signal.
+    ///
+    /// The mel scale is Slaney, not HTK: librosa's default is `htk=False`, and matching its output
+    /// takes precedence. Set `mel_scale` to [`MelScale::Htk`] for `htk=True`.
+    pub fn librosa_default(sample_rate: u32) -> Self {
+        MelConfig {
+            sample_rate,
+            max_duration_secs: None,
+            frame_length: 2048,
+            hop_length: 512,
+            n_mels: 128,
+            n_fft: 1025,
+            max_freq: sample_rate as f32 / 2.0,
+            drop_last_frame: false,
+            output_scale: SpectrogramScale::Raw,
+            drc: None,
+            top_db: None,
+            ..MelConfig::whisper_default()
+        }
     }
 
-    let fft_freqs_hz = linspace(0.0, nyquist, num_frequency_bins);
+    /// An approximation of Kaldi's `compute-fbank-feats` front end at 16 kHz: 25 ms Hamming frames
+    /// every 10 ms, 0.97 pre-emphasis, 23 unnormalised HTK-scale mels from 20 Hz and natural-log
+    /// power. Kaldi's dither, per-frame DC removal and power-of-two FFT padding are not reproduced,
+    /// frames are centred rather than snipped at the edges, and the triangles are built in Hz
+    /// rather than interpolated in mel, so the output is not bit-exact with Kaldi and individual
+    /// bins can differ from `compute-fbank-feats` by well over 0.01 dB.
+    pub fn kaldi_default() -> Self {
+        MelConfig {
+            max_duration_secs: None,
+            n_mels: 23,
+            min_freq: 20.0,
+            use_slaney_norm: false,
+            mel_scale: MelScale::Htk,
+            drop_last_frame: false,
+            window_type: WindowType::Hamming,
+            log_base: LogBase::Ln,
+            log_floor: f32::EPSILON,
+            drc: None,
+            top_db: None,
+            preemphasis_coeff: Some(0.97),
+            ..MelConfig::whisper_default()
+        }
+    }
 
-    let mut fft_freqs_mel = Vec::with_capacity(num_frequency_bins);
-    for freq_hz in fft_freqs_hz.iter() {
-        fft_freqs_mel.push(hertz_to_mel_slaney(*freq_hz));
+    /// Starts a builder seeded with the Whisper defaults.
+    pub fn builder() -> MelConfigBuilder {
+        MelConfigBuilder {
+            config: MelConfig::whisper_default(),
+        }
     }
 
-    let mut mel_filters = create_triangular_filter_bank(&fft_freqs_hz, &filter_freqs_hz)?;
-    
-    if use_slaney_norm {
-        let mut slaney_norm_factors = Vec::with_capacity(num_mel_filters);
-        for i in 0..num_mel_filters {
-            let left_hz = filter_freqs_hz[i];
-            let right_hz = filter_freqs_hz[i+2];
-            let width = right_hz - left_hz;
+    /// `max_duration_secs` in samples at `sample_rate`.
+    pub fn max_samples(&self) -> Option<usize> {
+        self.max_duration_secs.map(|secs| (secs.max(0.0) * self.sample_rate as f32).round() as usize)
+    }
 
-            if width > f32::EPSILON {
-                slaney_norm_factors.push(2.0 / width);
-            } else {
-                eprintln!("Warning: Mel filter {} has zero or negative width ({}), setting norm factor to 0.", i, width);
-                slaney_norm_factors.push(0.0);
+    pub fn validate(&self) -> Result<(), MelError> {
+        if self.sample_rate == 0 {
+            return Err(MelError::InvalidConfig("sample_rate must be positive".to_string()));
+        }
+        if let Some(secs) = self.max_duration_secs {
+            if !secs.is_finite() || self.max_samples() == Some(0) {
+                return Err(MelError::InvalidConfig(format!(
+                    "max_duration_secs ({}) must be finite and cover at least one sample",
+                    secs
+                )));
             }
         }
-
-        for row in mel_filters.iter_mut() {
-            for m in 0..num_mel_filters {
-                row[m] *= slaney_norm_factors[m];
+        if self.hop_length == 0 || self.hop_length >= self.frame_length {
+            return Err(MelError::InvalidConfig(format!(
+                "hop_length ({}) must be positive and smaller than frame_length ({})",
+                self.hop_length, self.frame_length
+            )));
+        }
+        if self.n_fft != self.frame_length / 2 + 1 {
+            return Err(MelError::InvalidConfig(format!(
+                "n_fft ({}) must equal frame_length / 2 + 1 ({})",
+                self.n_fft, self.frame_length / 2 + 1
+            )));
+        }
+        if self.n_mels == 0 {
+            return Err(MelError::InvalidConfig("n_mels must be positive".to_string()));
+        }
+        let nyquist = self.sample_rate as f32 / 2.0;
+        if !(self.min_freq >= 0.0 && self.min_freq < self.max_freq && self.max_freq <= nyquist) {
+            return Err(MelError::InvalidConfig(format!(
+                "frequency range must satisfy 0 <= min_freq ({}) < max_freq ({}) <= nyquist ({})",
+                self.min_freq, self.max_freq, nyquist
+            )));
+        }
+        if !self.filter_overlap.is_finite() || self.filter_overlap < 0.0 {
+            return Err(MelError::InvalidConfig(format!(
+                "filter_overlap ({}) must be finite and non-negative",
+                self.filter_overlap
+            )));
+        }
+        if self.log_floor.is_nan() || self.log_floor <= 0.0 {
+            return Err(MelError::InvalidConfig(format!(
+                "log_floor ({}) must be positive",
+                self.log_floor
+            )));
+        }
+        if let Some(top_db) = self.top_db {
+            if top_db.is_nan() || top_db < 0.0 {
+                return Err(MelError::InvalidConfig(format!(
+                    "top_db ({}) must be non-negative",
+                    top_db
+                )));
             }
         }
-    }
-    
-    Ok(mel_filters)
-} 
-
-fn apply_log(mut mel_spectrogram: Vec<Vec<f32>>) -> Result<Vec<Vec<f32>>, String> {
-    let epsilon = 1e-10f32;
-    
-    for row in mel_spectrogram.iter_mut() {
-        for val in row.iter_mut() {
-            if val.is_nan() {
-                return Err("Encountered nan value in mel spectrogram".to_string());
+        if let Some(alpha) = self.preemphasis_coeff {
+            if !(0.0..=1.0).contains(&alpha) {
+                return Err(MelError::InvalidConfig(format!(
+                    "preemphasis_coeff ({}) must be in [0, 1]",
+                    alpha
+                )));
+            }
+        }
+        if self.a_weighting && self.sample_rate <= 2000 {
+            return Err(MelError::InvalidConfig(format!(
+                "a_weighting needs a sample_rate above 2000 Hz, got {}",
+                self.sample_rate
+            )));
+        }
+        if let Some(noise) = &self.noise_aug {
+            if cfg!(not(feature = "augment")) {
+                return Err(MelError::InvalidConfig("noise_aug requires the augment feature".to_string()));
+            }
+            if noise.snr_db.is_nan() {
+                return Err(MelError::InvalidConfig("noise_aug snr_db must not be NaN".to_string()));
+            }
+        }
+        if let Some(vad) = &self.vad {
+            if vad.window_length == 0 {
+                return Err(MelError::InvalidConfig("vad window_length must be greater than 0".to_string()));
+            }
+            if vad.energy_threshold_db.is_nan() {
+                return Err(MelError::InvalidConfig("vad energy_threshold_db must not be NaN".to_string()));
+            }
+        }
+        if let Some(drc) = &self.drc {
+            if drc.scale == 0.0 || !drc.scale.is_finite() {
+                return Err(MelError::InvalidConfig(format!("DRC scale ({}) must be finite and non-zero", drc.scale)));
+            }
+        }
+        if let NormalizationMode::Pcen(pcen) = &self.normalization {
+            let valid = pcen.smooth > 0.0 && pcen.smooth <= 1.0 && pcen.root > 0.0 && pcen.floor > 0.0;
+            if !valid {
+                return Err(MelError::InvalidConfig(format!(
+                    "PCEN needs 0 < smooth <= 1, root > 0 and floor > 0, got {:?}",
+                    pcen
+                )));
+            }
+        }
+        if let Some(semitones) = self.pitch_shift_semitones {
+            if !semitones.is_finite() {
+                return Err(MelError::InvalidConfig(format!(
+                    "pitch_shift_semitones ({}) must be finite",
+                    semitones
+                )));
+            }
+        }
+        if let Some(rate) = self.time_stretch_rate {
+            if !rate.is_finite() || rate <= 0.0 {
+                return Err(MelError::InvalidConfig(format!("time_stretch_rate ({}) must be positive", rate)));
             }
-            *val = (val.max(epsilon)).log10();
         }
+        if let Some(top_db) = self.trim_top_db {
+            if top_db.is_nan() || top_db <= 0.0 {
+                return Err(MelError::InvalidConfig(format!("trim_top_db ({}) must be positive", top_db)));
+            }
+        }
+        if let Some(CmvnMode::SlidingWindow { width: 0 }) = self.cmvn {
+            return Err(MelError::InvalidConfig("CMVN window width must be greater than 0".to_string()));
+        }
+        if self.delta_order > 2 {
+            return Err(MelError::InvalidConfig(format!(
+                "delta_order ({}) must be 0, 1 or 2",
+                self.delta_order
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Default for MelConfig {
+    fn default() -> Self {
+        MelConfig::whisper_default()
     }
+}
 
-    Ok(mel_spectrogram)
+#[derive(Debug, Clone)]
+pub struct MelConfigBuilder {
+    config: MelConfig,
 }
 
-fn apply_dynamic_range_compression(mut mel_log_spectrogram: Vec<Vec<f32>>) -> Result<Vec<Vec<f32>>, String> {
-    if mel_log_spectrogram.is_empty() || mel_log_spectrogram[0].is_empty() {
-        return Ok(mel_log_spectrogram);
+impl MelConfigBuilder {
+    pub fn sample_rate(mut self, sample_rate: u32) -> Self {
+        self.config.sample_rate = sample_rate;
+        self
     }
 
-    let mut max_val = f32::NEG_INFINITY;
-    for row in mel_log_spectrogram.iter() {
-        for val in row.iter() {
-            if *val > max_val {
-                max_val = *val;
-            }
-        }
+    pub fn max_duration_secs(mut self, max_duration_secs: Option<f32>) -> Self {
+        self.config.max_duration_secs = max_duration_secs;
+        self
     }
 
-    let floor_val = max_val - 8.0;
+    pub fn frame_length(mut self, frame_length: usize) -> Self {
+        self.config.frame_length = frame_length;
+        self
+    }
 
-    for row in mel_log_spectrogram.iter_mut() {
-        for val in row.iter_mut() {
-            *val = val.max(floor_val);
-            *val = (*val + 4.0) / 4.0; 
-        }
+    pub fn hop_length(mut self, hop_length: usize) -> Self {
+        self.config.hop_length = hop_length;
+        self
     }
 
-    Ok(mel_log_spectrogram)
-}
+    pub fn n_mels(mut self, n_mels: usize) -> Self {
+        self.config.n_mels = n_mels;
+        self
+    }
 
-#[no_mangle]
-pub extern "C" fn extract_whisper_features(path: *const c_char) -> MelSpectrogramData {
-    let c_str = unsafe { CStr::from_ptr(path) };
-    let path_str = match c_str.to_str() {
-        Ok(s) => s,
-        Err(_) => {
-            eprintln!("Invalid UTF-8 path");
-            return MelSpectrogramData::default();
-        }
-    };
+    pub fn n_fft(mut self, n_fft: usize) -> Self {
+        self.config.n_fft = n_fft;
+        self
+    }
 
-    let (mono, orig_sample_rate) = match read_wav(path_str) {
-        Ok(v) => v,
-        Err(err) => {
-            eprintln!("{}", err);
-            return MelSpectrogramData::default();
-        }
-    };
+    pub fn min_freq(mut self, min_freq: f32) -> Self {
+        self.config.min_freq = min_freq;
+        self
+    }
 
-    let resampled = match resample_audio(mono, orig_sample_rate, 16000) {
-        Ok(buf) => buf,
-        Err(err) => {
-            eprintln!("{}", err);
-            return MelSpectrogramData::default();
-        }
-    };
+    pub fn max_freq(mut self, max_freq: f32) -> Self {
+        self.config.max_freq = max_freq;
+        self
+    }
 
-    let padded = match pad_or_truncate(resampled, 480000, 400) {
-        Ok(v) => v,
-        Err(err) => {
-            eprintln!("{}", err);
-            return MelSpectrogramData::default();
-        }
-    };
+    pub fn use_slaney_norm(mut self, use_slaney_norm: bool) -> Self {
+        self.config.use_slaney_norm = use_slaney_norm;
+        self
+    }
 
-    let framed = match frame_signal(padded, 400, 160) {
-        Ok(v) => v,
-        Err(err) => {
-            eprint!("{}", err);
-            return MelSpectrogramData::default();
-        }
-    };
+    pub fn mel_scale(mut self, mel_scale: MelScale) -> Self {
+        self.config.mel_scale = mel_scale;
+        self
+    }
 
-    let hann_weighted = match apply_hann_window(framed) {
-        Ok(v) => v,
-        Err(err) => {
-            eprint!("{}", err);
-            return MelSpectrogramData::default();
-        }
-    };
+    pub fn resampling_quality(mut self, resampling_quality: ResamplingQuality) -> Self {
+        self.config.resampling_quality = resampling_quality;
+        self
+    }
 
-    let rfft_spectrogram = match apply_rfft(hann_weighted) {
-        Ok(v) => v,
-        Err(err) => {
-            eprint!("{}", err);
-            return MelSpectrogramData::default();
-        }
-    };
+    pub fn filter_overlap(mut self, filter_overlap: f32) -> Self {
+        self.config.filter_overlap = filter_overlap;
+        self
+    }
 
-    let power_spec = match power_spectrogram(rfft_spectrogram) {
-        Ok(v) => v,
-        Err(err) => {
-            eprint!("{}", err);
-            return MelSpectrogramData::default();
-        }
-    };
+    pub fn filter_bank_type(mut self, filter_bank_type: FilterBankType) -> Self {
+        self.config.filter_bank_type = filter_bank_type;
+        self
+    }
 
-    let mel_filters = match mel_filter_bank(201, 80, 0.0, 8000.0, 16000, true) {
-        Ok(v) => v,
-        Err(err) => {
-            eprint!("{}", err);
-            return MelSpectrogramData::default();
-        }
-    };
+    pub fn downmix(mut self, downmix: DownmixStrategy) -> Self {
+        self.config.downmix = downmix;
+        self
+    }
 
-    
-    let mut mel_spectrogram = vec![vec![0.0f32; 3000]; 80];
+    pub fn noise_aug(mut self, noise_aug: Option<NoiseAugConfig>) -> Self {
+        self.config.noise_aug = noise_aug;
+        self
+    }
 
-    for i in 0..3000 {
-        for m in 0..80 {
-            let mut sum = 0.0;
-            for k in 0..201 {
-                sum += power_spec[i][k] * mel_filters[k][m];
-            }
-        mel_spectrogram[m][i] = sum;
-        }
+    pub fn padding_mode(mut self, padding_mode: PaddingMode) -> Self {
+        self.config.padding_mode = padding_mode;
+        self
     }
 
-    let mel_log_spectrogram = match apply_log(mel_spectrogram) {
-        Ok(v) => v,
-        Err(err) => {
-            eprintln!("{}", err);
-            return MelSpectrogramData::default();
-        }
-    };
+    pub fn drop_last_frame(mut self, drop_last_frame: bool) -> Self {
+        self.config.drop_last_frame = drop_last_frame;
+        self
+    }
 
-    let final_spectrogram = match apply_dynamic_range_compression(mel_log_spectrogram) {
-         Ok(v) => v,
-         Err(err) => {
-             eprintln!("Error during dynamic range compression: {}", err);
-             return MelSpectrogramData::default();
-         }
-    };
+    pub fn window_type(mut self, window_type: WindowType) -> Self {
+        self.config.window_type = window_type;
+        self
+    }
 
-    let flat_spectrogram: Vec<f32> = final_spectrogram.into_iter().flatten().collect();
+    pub fn log_base(mut self, log_base: LogBase) -> Self {
+        self.config.log_base = log_base;
+        self
+    }
 
-    let leaked_slice = flat_spectrogram.into_boxed_slice();
-    let data_ptr = Box::leak(leaked_slice).as_mut_ptr();
+    pub fn log_floor(mut self, log_floor: f32) -> Self {
+        self.config.log_floor = log_floor;
+        self
+    }
 
-    MelSpectrogramData {
-        data: data_ptr,
-        n_frames: 3000,
-        n_mels: 80,
+    pub fn spectrogram_type(mut self, spectrogram_type: SpectrogramType) -> Self {
+        self.config.spectrogram_type = spectrogram_type;
+        self
     }
-}
 
-#[no_mangle]
-pub unsafe extern "C" fn free_spectrogram_data(spectrogram_data: MelSpectrogramData) {
-    if !spectrogram_data.data.is_null() {
-        let len = spectrogram_data.n_frames * spectrogram_data.n_mels;
+    pub fn drc(mut self, drc: Option<DrcConfig>) -> Self {
+        self.config.drc = drc;
+        self
+    }
 
-        let slice = slice::from_raw_parts_mut(spectrogram_data.data, len);
-        let b = Box::from_raw(slice);
+    pub fn output_scale(mut self, output_scale: SpectrogramScale) -> Self {
+        self.config.output_scale = output_scale;
+        self
+    }
 
-        drop(b);
+    pub fn top_db(mut self, top_db: Option<f32>) -> Self {
+        self.config.top_db = top_db;
+        self
+    }
+
+    pub fn preemphasis_coeff(mut self, preemphasis_coeff: Option<f32>) -> Self {
+        self.config.preemphasis_coeff = preemphasis_coeff;
+        self
+    }
+
+    pub fn a_weighting(mut self, a_weighting: bool) -> Self {
+        self.config.a_weighting = a_weighting;
+        self
+    }
+
+    pub fn pitch_shift_semitones(mut self, pitch_shift_semitones: Option<f32>) -> Self {
+        self.config.pitch_shift_semitones = pitch_shift_semitones;
+        self
+    }
+
+    pub fn time_stretch_rate(mut self, time_stretch_rate: Option<f32>) -> Self {
+        self.config.time_stretch_rate = time_stretch_rate;
+        self
+    }
+
+    pub fn vad(mut self, vad: Option<VadConfig>) -> Self {
+        self.config.vad = vad;
+        self
+    }
+
+    pub fn trim_top_db(mut self, trim_top_db: Option<f32>) -> Self {
+        self.config.trim_top_db = trim_top_db;
+        self
+    }
+
+    pub fn normalization(mut self, normalization: NormalizationMode) -> Self {
+        self.config.normalization = normalization;
+        self
+    }
+
+    pub fn cmvn(mut self, cmvn: Option<CmvnMode>) -> Self {
+        self.config.cmvn = cmvn;
+        self
+    }
+
+    pub fn include_log_energy(mut self, include_log_energy: bool) -> Self {
+        self.config.include_log_energy = include_log_energy;
+        self
     }
-}
 
+    pub fn include_zero_crossing_rate(mut self, include_zero_crossing_rate: bool) -> Self {
+        self.config.include_zero_crossing_rate = include_zero_crossing_rate;
+        self
+    }
 
+    pub fn include_spectral_centroid(mut self, include_spectral_centroid: bool) -> Self {
+        self.config.include_spectral_centroid = include_spectral_centroid;
+        self
+    }
 
+    pub fn include_spectral_flux(mut self, include_spectral_flux: bool) -> Self {
+        self.config.include_spectral_flux = include_spectral_flux;
+        self
+    }
 
+    pub fn extract_f0(mut self, extract_f0: bool) -> Self {
+        self.config.extract_f0 = extract_f0;
+        self
+    }
+
+    pub fn delta_order(mut self, delta_order: u8) -> Self {
+        self.config.delta_order = delta_order;
+        self
+    }
+
+    pub fn post_normalize(mut self, post_normalize: PostNormalizeMode) -> Self {
+        self.config.post_normalize = post_normalize;
+        self
+    }
+
+    pub fn build(self) -> Result<MelConfig, MelError> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+std_only! {
+/// An owned log-mel spectrogram, stored frame-major (`n_frames` rows of `n_mels` values).
+#[derive(Debug, Clone, PartialEq)]
+pub struct MelSpectrogram {
+    data: Vec<f32>,
+    n_frames: usize,
+    n_mels: usize,
+    sample_rate: u32,
+    hop_length: usize,
+    log_energy: Option<Vec<f32>>,
+    zero_crossing_rate: Option<Vec<f32>>,
+    spectral_centroid: Option<Vec<f32>>,
+    spectral_flux: Option<Vec<f32>>,
+    f0: Option<Vec<Option<f32>>>,
+}
+
+impl MelSpectrogram {
+    pub fn as_slice(&self) -> &[f32] {
+        &self.data
+    }
+
+    pub fn n_frames(&self) -> usize {
+        self.n_frames
+    }
+
+    pub fn n_mels(&self) -> usize {
+        self.n_mels
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn hop_length(&self) -> usize {
+        self.hop_length
+    }
+
+    /// Returns the `n_mels` values of frame `i`. Panics if `i >= n_frames`.
+    pub fn get_frame(&self, i: usize) -> &[f32] {
+        &self.data[i * self.n_mels..(i + 1) * self.n_mels]
+    }
+
+    /// The `n_mels` values of frame `i`, or `None` if `i >= n_frames`.
+    pub fn try_get_frame(&self, i: usize) -> Option<&[f32]> {
+        (i < self.n_frames).then(|| self.get_frame(i))
+    }
+
+    /// Returns the `n_frames` values of mel bin `m` over time. Panics if `m >= n_mels`.
+    pub fn get_mel_bin(&self, m: usize) -> Vec<f32> {
+        assert!(m < self.n_mels, "mel bin {} out of range for {} bins", m, self.n_mels);
+        (0..self.n_frames).map(|t| self.data[t * self.n_mels + m]).collect()
+    }
+
+    /// The `n_frames` values of mel bin `m` over time, or `None` if `m >= n_mels`.
+    pub fn try_get_mel_bin(&self, m: usize) -> Option<Vec<f32>> {
+        (m < self.n_mels).then(|| self.get_mel_bin(m))
+    }
+
+    /// Frames in time order, each `n_mels` values long.
+    pub fn iter_frames(&self) -> impl Iterator<Item = &[f32]> {
+        (0..self.n_frames).map(|t| self.get_frame(t))
+    }
+
+    /// Mel bins from lowest to highest, each `n_frames` values long.
+    pub fn iter_mel_bins(&self) -> impl Iterator<Item = Vec<f32>> + '_ {
+        (0..self.n_mels).map(|m| self.get_mel_bin(m))
+    }
+
+    /// Per-frame log energy, present when `MelConfig::include_log_energy` is set.
+    pub fn log_energy(&self) -> Option<&[f32]> {
+        self.log_energy.as_deref()
+    }
+
+    /// Per-frame zero-crossing rate, present when `MelConfig::include_zero_crossing_rate` is set.
+    pub fn zero_crossing_rate(&self) -> Option<&[f32]> {
+        self.zero_crossing_rate.as_deref()
+    }
+
+    /// Per-frame spectral centroid in Hz, present when `MelConfig::include_spectral_centroid` is set.
+    pub fn spectral_centroid(&self) -> Option<&[f32]> {
+        self.spectral_centroid.as_deref()
+    }
+
+    /// Per-frame spectral flux, present when `MelConfig::include_spectral_flux` is set.
+    pub fn spectral_flux(&self) -> Option<&[f32]> {
+        self.spectral_flux.as_deref()
+    }
+
+    /// Per-frame fundamental frequency in Hz (`None` when unvoiced), present when
+    /// `MelConfig::extract_f0` is set.
+    pub fn f0(&self) -> Option<&[Option<f32>]> {
+        self.f0.as_deref()
+    }
+
+    pub fn into_vec(self) -> Vec<f32> {
+        self.data
+    }
+
+    /// A spectrogram without optional tracks from frame-major `data`, as read back from disk.
+    fn from_parts(
+        data: Vec<f32>,
+        n_frames: usize,
+        n_mels: usize,
+        sample_rate: u32,
+        hop_length: usize,
+    ) -> Result<Self, MelError> {
+        if data.len() != n_frames * n_mels {
+            return Err(MelError::InvalidConfig(format!(
+                "Expected {} x {} values, found {}",
+                n_frames, n_mels, data.len()
+            )));
+        }
+        Ok(MelSpectrogram {
+            data,
+            n_frames,
+            n_mels,
+            sample_rate,
+            hop_length,
+            log_energy: None,
+            zero_crossing_rate: None,
+            spectral_centroid: None,
+            spectral_flux: None,
+            f0: None,
+        })
+    }
+}
+
+/// Mid and side spectrograms of a stereo pair, `(left + right) / 2` and `(left - right) / 2`
+/// element-wise. Both take their timing from `left` and carry no optional tracks.
+pub fn stereo_mel_features(left: &MelSpectrogram, right: &MelSpectrogram) -> Result<(MelSpectrogram, MelSpectrogram), MelError> {
+    if left.n_frames != right.n_frames || left.n_mels != right.n_mels {
+        return Err(MelError::InvalidConfig(format!(
+            "Channel spectrograms differ: {} x {} and {} x {}",
+            left.n_frames, left.n_mels, right.n_frames, right.n_mels
+        )));
+    }
+    let (mid, side) = left.data.iter()
+        .zip(&right.data)
+        .map(|(&l, &r)| ((l + r) / 2.0, (l - r) / 2.0))
+        .unzip();
+    let channel = |data| MelSpectrogram::from_parts(data, left.n_frames, left.n_mels, left.sample_rate, left.hop_length);
+    Ok((channel(mid)?, channel(side)?))
+}
+
+/// Writes `matrix` one row per line, optionally preceded by a comma-joined header line whose
+/// length must match the number of columns.
+#[cfg(all(feature = "std", not(feature = "wasm")))]
+pub fn save_matrix_as_csv(matrix: &[Vec<f32>], path: &str, headers: Option<Vec<String>>) -> Result<(), MelError> {
+    let n_cols = matrix.first().map_or(0, Vec::len);
+    if matrix.iter().any(|row| row.len() != n_cols) {
+        return Err(MelError::InvalidConfig("All rows must have the same number of columns".to_string()));
+    }
+
+    if let Some(headers) = &headers {
+        if !matrix.is_empty() && headers.len() != n_cols {
+            return Err(MelError::InvalidConfig(format!(
+                "{} headers given for {} columns",
+                headers.len(),
+                n_cols
+            )));
+        }
+    }
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    if let Some(headers) = headers {
+        writeln!(writer, "{}", headers.join(","))?;
+    }
+    for row in matrix {
+        let line: Vec<String> = row.iter().map(f32::to_string).collect();
+        writeln!(writer, "{}", line.join(","))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Reads a matrix written by [`save_matrix_as_csv`], skipping the first line when `has_header`
+/// is set. Every row must have as many columns as the first.
+#[cfg(all(feature = "std", not(feature = "wasm")))]
+pub fn load_matrix_from_csv(path: &str, has_header: bool) -> Result<Vec<Vec<f32>>, MelError> {
+    let contents = std::fs::read_to_string(path)?;
+    let mut matrix: Vec<Vec<f32>> = Vec::new();
+    for (index, line) in contents.lines().enumerate().skip(usize::from(has_header)) {
+        if line.is_empty() {
+            continue;
+        }
+        let row = line.split(',')
+            .map(|value| value.trim().parse::<f32>())
+            .collect::<Result<Vec<f32>, _>>()
+            .map_err(|e| MelError::UnsupportedFormat(format!("CSV line {}: {}", index + 1, e)))?;
+        if let Some(first) = matrix.first() {
+            if row.len() != first.len() {
+                return Err(MelError::UnsupportedFormat(format!(
+                    "CSV line {} has {} columns, expected {}",
+                    index + 1,
+                    row.len(),
+                    first.len()
+                )));
+            }
+        }
+        matrix.push(row);
+    }
+    Ok(matrix)
+}
+
+/// Writes `features` as a Kaldi text archive, each utterance a frame-per-row matrix keyed by its
+/// id, and a script file mapping every id to `ark_path:offset` of its matrix. Ids must be
+/// non-empty and free of whitespace.
+#[cfg(all(feature = "std", not(feature = "wasm")))]
+pub fn save_kaldi_text_ark(
+    features: &[(String, MelSpectrogram)],
+    ark_path: &str,
+    scp_path: &str,
+) -> Result<(), MelError> {
+    if let Some((id, _)) = features.iter().find(|(id, _)| id.is_empty() || id.contains(char::is_whitespace)) {
+        return Err(MelError::InvalidConfig(format!("Invalid Kaldi utterance id {:?}", id)));
+    }
+
+    let mut ark = BufWriter::new(File::create(ark_path)?);
+    let mut scp = BufWriter::new(File::create(scp_path)?);
+    let mut offset = 0;
+    for (id, spec) in features {
+        let mut entry = format!("{} ", id);
+        // The offset points just past the key, where Kaldi's matrix reader starts.
+        writeln!(scp, "{} {}:{}", id, ark_path, offset + entry.len())?;
+        entry.push_str(" [");
+        for t in 0..spec.n_frames() {
+            entry.push_str("\n  ");
+            for value in spec.get_frame(t) {
+                entry.push_str(&format!("{} ", value));
+            }
+        }
+        entry.push_str("]\n");
+        ark.write_all(entry.as_bytes())?;
+        offset += entry.len();
+    }
+    ark.flush()?;
+    scp.flush()?;
+    Ok(())
+}
+
+/// Writes `matrix` as a NumPy v1.0 `.npy` file holding a C-order `float32` array of shape
+/// `(rows, cols)`, loadable with `np.load`.
+#[cfg(all(feature = "std", not(feature = "wasm")))]
+pub fn save_as_npy(matrix: &[Vec<f32>], path: &str) -> Result<(), MelError> {
+    let n_cols = matrix.first().map_or(0, Vec::len);
+    if matrix.iter().any(|row| row.len() != n_cols) {
+        return Err(MelError::InvalidConfig("All rows must have the same number of columns".to_string()));
+    }
+
+    let mut header = format!(
+        "{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {}), }}",
+        matrix.len(),
+        n_cols
+    );
+    // Magic (6) + version (2) + header length (2) + header, padded with spaces to a multiple of 64.
+    let unpadded = 10 + header.len() + 1;
+    header.push_str(&" ".repeat(unpadded.next_multiple_of(64) - unpadded));
+    header.push('\n');
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(b"\x93NUMPY\x01\x00")?;
+    writer.write_all(&(header.len() as u16).to_le_bytes())?;
+    writer.write_all(header.as_bytes())?;
+    for value in matrix.iter().flatten() {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Arrow schema matching [`spectrogram_to_record_batch`]: a non-null `frame` int64 column
+/// followed by float32 columns `mel_0` … `mel_{n_mels - 1}`.
+#[cfg(feature = "arrow")]
+pub fn spectrogram_arrow_schema(spec: &MelSpectrogram) -> arrow2::datatypes::Schema {
+    use arrow2::datatypes::{DataType, Field};
+
+    let fields: Vec<Field> = std::iter::once(Field::new("frame", DataType::Int64, false))
+        .chain((0..spec.n_mels()).map(|m| Field::new(format!("mel_{}", m), DataType::Float32, false)))
+        .collect();
+    fields.into()
+}
+
+/// One row per frame: the frame index followed by one column per mel bin. Pair it with
+/// [`spectrogram_arrow_schema`] for the column names.
+#[cfg(feature = "arrow")]
+pub fn spectrogram_to_record_batch(spec: &MelSpectrogram) -> arrow2::chunk::Chunk<Box<dyn arrow2::array::Array>> {
+    use arrow2::array::{Array, Float32Array, Int64Array};
+
+    let (n_frames, n_mels) = (spec.n_frames(), spec.n_mels());
+    let data = spec.as_slice();
+    let frame_column: Box<dyn Array> = Int64Array::from_vec((0..n_frames as i64).collect()).boxed();
+    let mel_columns = (0..n_mels).map(|m| {
+        Float32Array::from_vec((0..n_frames).map(|t| data[t * n_mels + m]).collect()).boxed()
+    });
+
+    arrow2::chunk::Chunk::new(std::iter::once(frame_column).chain(mel_columns).collect())
+}
+
+/// Stores `spec` mel-major, as an `(n_mels, n_frames)` float32 dataset named `dataset_name`,
+/// with `n_mels`, `n_frames`, `sample_rate` and `hop_length` attributes.
+#[cfg(all(feature = "hdf5", not(feature = "wasm")))]
+pub fn save_spectrogram_hdf5(spec: &MelSpectrogram, path: &str, dataset_name: &str) -> Result<(), MelError> {
+    let (n_frames, n_mels) = (spec.n_frames(), spec.n_mels());
+    let data = spec.as_slice();
+    let mel_major: Vec<f32> = (0..n_mels)
+        .flat_map(|m| (0..n_frames).map(move |t| data[t * n_mels + m]))
+        .collect();
+
+    let file = hdf5::File::create(path)?;
+    let dataset = file.new_dataset::<f32>().shape((n_mels, n_frames)).create(dataset_name)?;
+    dataset.write_raw(mel_major.as_slice())?;
+    for (name, value) in [
+        ("n_mels", n_mels as u64),
+        ("n_frames", n_frames as u64),
+        ("sample_rate", spec.sample_rate() as u64),
+        ("hop_length", spec.hop_length() as u64),
+    ] {
+        dataset.new_attr::<u64>().shape(()).create(name)?.write_scalar(&value)?;
+    }
+    Ok(())
+}
+
+/// Reads a dataset written by [`save_spectrogram_hdf5`].
+#[cfg(all(feature = "hdf5", not(feature = "wasm")))]
+pub fn load_spectrogram_hdf5(path: &str, dataset_name: &str) -> Result<MelSpectrogram, MelError> {
+    let file = hdf5::File::open(path)?;
+    let dataset = file.dataset(dataset_name)?;
+    let read_attr = |name: &str| -> Result<u64, MelError> { Ok(dataset.attr(name)?.read_scalar::<u64>()?) };
+    let (n_mels, n_frames) = (read_attr("n_mels")? as usize, read_attr("n_frames")? as usize);
+    if dataset.shape() != [n_mels, n_frames] {
+        return Err(MelError::UnsupportedFormat(format!(
+            "Dataset shape {:?} does not match its n_mels ({}) and n_frames ({}) attributes",
+            dataset.shape(), n_mels, n_frames
+        )));
+    }
+
+    let mel_major: Vec<f32> = dataset.read_raw()?;
+    let data = (0..n_frames)
+        .flat_map(|t| (0..n_mels).map(|m| mel_major[m * n_frames + t]).collect::<Vec<_>>())
+        .collect();
+    MelSpectrogram::from_parts(
+        data,
+        n_frames,
+        n_mels,
+        read_attr("sample_rate")? as u32,
+        read_attr("hop_length")? as usize,
+    )
+}
+
+/// JSON shape of a spectrogram: `data` holds `n_mels` rows of `n_frames` values.
+#[cfg(feature = "json")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SpectrogramJson {
+    n_mels: usize,
+    n_frames: usize,
+    sample_rate: u32,
+    hop_length: usize,
+    data: Vec<Vec<f32>>,
+}
+
+/// Serialises `spec` as `{"n_mels", "n_frames", "sample_rate", "hop_length", "data"}` with
+/// `data` mel-major. Every value is written as decimal text, so 30 s (3000 frames) of 80 mels
+/// is already close to 3 MB and longer inputs soon reach 10 MB; prefer a binary format there.
+#[cfg(feature = "json")]
+pub fn spectrogram_to_json(spec: &MelSpectrogram) -> Result<String, MelError> {
+    let (n_frames, n_mels) = (spec.n_frames(), spec.n_mels());
+    let data = spec.as_slice();
+    let json = SpectrogramJson {
+        n_mels,
+        n_frames,
+        sample_rate: spec.sample_rate(),
+        hop_length: spec.hop_length(),
+        data: (0..n_mels).map(|m| (0..n_frames).map(|t| data[t * n_mels + m]).collect()).collect(),
+    };
+    serde_json::to_string(&json).map_err(|e| MelError::InvalidAudio(format!("JSON: {}", e)))
+}
+
+/// Parses the output of [`spectrogram_to_json`].
+#[cfg(feature = "json")]
+pub fn spectrogram_from_json(s: &str) -> Result<MelSpectrogram, MelError> {
+    let json: SpectrogramJson =
+        serde_json::from_str(s).map_err(|e| MelError::UnsupportedFormat(format!("JSON: {}", e)))?;
+    if json.data.len() != json.n_mels || json.data.iter().any(|row| row.len() != json.n_frames) {
+        return Err(MelError::UnsupportedFormat(format!(
+            "JSON data is not {} rows of {} values",
+            json.n_mels, json.n_frames
+        )));
+    }
+
+    let data = (0..json.n_frames)
+        .flat_map(|t| json.data.iter().map(move |row| row[t]))
+        .collect();
+    MelSpectrogram::from_parts(data, json.n_frames, json.n_mels, json.sample_rate, json.hop_length)
+}
+
+const BINARY_MAGIC: &[u8; 4] = b"MELS";
+const BINARY_VERSION: u16 = 1;
+
+/// Writes `spec` as a 16-byte little-endian header (`MELS`, version `u16`, n_mels `u16`,
+/// n_frames `u32`, sample_rate `u32`) followed by the frame-major `f32` values. The hop length
+/// is not stored.
+pub fn save_spectrogram_binary(spec: &MelSpectrogram, w: &mut impl Write) -> Result<(), MelError> {
+    let n_mels = u16::try_from(spec.n_mels())
+        .map_err(|_| MelError::InvalidConfig(format!("n_mels ({}) does not fit in a u16", spec.n_mels())))?;
+    let n_frames = u32::try_from(spec.n_frames())
+        .map_err(|_| MelError::InvalidConfig(format!("n_frames ({}) does not fit in a u32", spec.n_frames())))?;
+
+    let mut bytes = Vec::with_capacity(16 + 4 * spec.as_slice().len());
+    bytes.extend_from_slice(BINARY_MAGIC);
+    bytes.extend_from_slice(&BINARY_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&n_mels.to_le_bytes());
+    bytes.extend_from_slice(&n_frames.to_le_bytes());
+    bytes.extend_from_slice(&spec.sample_rate().to_le_bytes());
+    for value in spec.as_slice() {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    w.write_all(&bytes)?;
+    Ok(())
+}
+
+/// Reads a spectrogram written by [`save_spectrogram_binary`]; its hop length reads as 0.
+pub fn load_spectrogram_binary(r: &mut impl Read) -> Result<MelSpectrogram, MelError> {
+    let mut header = [0u8; 16];
+    r.read_exact(&mut header)?;
+    if &header[0..4] != BINARY_MAGIC {
+        return Err(MelError::UnsupportedFormat("Missing MELS magic bytes".to_string()));
+    }
+    let version = u16::from_le_bytes([header[4], header[5]]);
+    if version != BINARY_VERSION {
+        return Err(MelError::UnsupportedFormat(format!("Unknown MELS version {}", version)));
+    }
+    let n_mels = u16::from_le_bytes([header[6], header[7]]) as usize;
+    let n_frames = u32::from_le_bytes([header[8], header[9], header[10], header[11]]) as usize;
+    let sample_rate = u32::from_le_bytes([header[12], header[13], header[14], header[15]]);
+
+    // Read through `take` rather than preallocating, so a corrupt header cannot force a huge
+    // allocation.
+    let expected = 4 * n_mels * n_frames;
+    let mut bytes = Vec::new();
+    r.take(expected as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != expected {
+        return Err(MelError::UnsupportedFormat(format!(
+            "MELS data truncated: expected {} bytes, found {}",
+            expected, bytes.len()
+        )));
+    }
+    let data = bytes.chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect();
+    MelSpectrogram::from_parts(data, n_frames, n_mels, sample_rate, 0)
+}
+
+/// Writes `spec` as a single-record `.tfrecord` file holding a `tf.train.Example` with the
+/// mel-major values as a `FloatList` under `mel_spectrogram` and the shape as `Int64List`
+/// features `n_frames` and `n_mels`.
+#[cfg(all(feature = "tfrecord", not(feature = "wasm")))]
+pub fn save_tfrecord(spec: &MelSpectrogram, path: &str) -> Result<(), MelError> {
+    let (n_frames, n_mels) = (spec.n_frames(), spec.n_mels());
+    let data = spec.as_slice();
+
+    // FloatList and Int64List both hold their values as a packed field 1.
+    let mut floats = Vec::with_capacity(4 * data.len());
+    for m in 0..n_mels {
+        for t in 0..n_frames {
+            floats.extend_from_slice(&data[t * n_mels + m].to_le_bytes());
+        }
+    }
+    let float_list = protobuf_field(1, &floats);
+    let int64_list = |value: usize| {
+        let mut varint = Vec::new();
+        protobuf_varint(&mut varint, value as u64);
+        protobuf_field(1, &varint)
+    };
+
+    // Feature is a oneof: 2 = float_list, 3 = int64_list. Features is a map<string, Feature>,
+    // encoded as repeated entries of {1: key, 2: value}.
+    let mut features = Vec::new();
+    for (key, feature) in [
+        ("mel_spectrogram", protobuf_field(2, &float_list)),
+        ("n_frames", protobuf_field(3, &int64_list(n_frames))),
+        ("n_mels", protobuf_field(3, &int64_list(n_mels))),
+    ] {
+        let mut entry = protobuf_field(1, key.as_bytes());
+        entry.extend(protobuf_field(2, &feature));
+        features.extend(protobuf_field(1, &entry));
+    }
+    let example = protobuf_field(1, &features);
+
+    let length = (example.len() as u64).to_le_bytes();
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(&length)?;
+    writer.write_all(&masked_crc32c(&length).to_le_bytes())?;
+    writer.write_all(&example)?;
+    writer.write_all(&masked_crc32c(&example).to_le_bytes())?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// A length-delimited (wire type 2) protobuf field.
+#[cfg(feature = "tfrecord")]
+fn protobuf_field(field_number: u32, payload: &[u8]) -> Vec<u8> {
+    let mut field = Vec::with_capacity(payload.len() + 6);
+    protobuf_varint(&mut field, ((field_number << 3) | 2) as u64);
+    protobuf_varint(&mut field, payload.len() as u64);
+    field.extend_from_slice(payload);
+    field
+}
+
+#[cfg(feature = "tfrecord")]
+fn protobuf_varint(buffer: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buffer.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
+}
+
+/// CRC-32C (Castagnoli) rotated and offset as TFRecord framing expects.
+#[cfg(feature = "tfrecord")]
+fn masked_crc32c(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0x82F6_3B78 } else { crc >> 1 };
+        }
+    }
+    let crc = !crc;
+    crc.rotate_right(15).wrapping_add(0xA282_EAD8)
+}
+
+/// Writes mono `samples` as a 32-bit float WAV, preserving values outside [-1.0, 1.0].
+#[cfg(all(feature = "std", not(feature = "wasm")))]
+pub fn save_wav_f32(path: &str, samples: &[f32], sample_rate: u32) -> Result<(), MelError> {
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate,
+        bits_per_sample: 32,
+        sample_format: SampleFormat::Float,
+    };
+    let mut writer = WavWriter::create(path, spec)?;
+    for &sample in samples {
+        writer.write_sample(sample)?;
+    }
+    writer.finalize()?;
+    Ok(())
+}
+
+/// Container detected by [`probe_audio`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioFormat {
+    Wav,
+    Flac,
+}
+
+/// Stream parameters read from a file header.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioFileInfo {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub bits_per_sample: u16,
+    /// 0 when a FLAC stream does not record its length.
+    pub duration_secs: f64,
+    pub format: AudioFormat,
+}
+
+/// Reads the WAV or FLAC header at `path` without decoding any samples. FLAC is probed from its
+/// STREAMINFO block, so the `flac` feature is not needed.
+#[cfg(all(feature = "std", not(feature = "wasm")))]
+pub fn probe_audio(path: &str) -> Result<AudioFileInfo, MelError> {
+    let mut header = Vec::with_capacity(42);
+    File::open(path)?.take(42).read_to_end(&mut header)?;
+    match header.as_slice() {
+        [b'f', b'L', b'a', b'C', block_type, _, _, _, info @ ..] if block_type & 0x7F == 0 && info.len() == 34 => {
+            // STREAMINFO: 20-bit sample rate, 3-bit channels - 1, 5-bit bits per sample - 1 and a
+            // 36-bit total sample count, packed from byte 10 of the block.
+            let packed = u64::from_be_bytes(info[10..18].try_into().unwrap());
+            let sample_rate = (packed >> 44) as u32;
+            let total_samples = packed & 0xF_FFFF_FFFF;
+            Ok(AudioFileInfo {
+                sample_rate,
+                channels: ((packed >> 41) & 0x7) as u16 + 1,
+                bits_per_sample: ((packed >> 36) & 0x1F) as u16 + 1,
+                duration_secs: if sample_rate > 0 { total_samples as f64 / sample_rate as f64 } else { 0.0 },
+                format: AudioFormat::Flac,
+            })
+        }
+        [b'f', b'L', b'a', b'C', ..] => Err(MelError::InvalidAudio("FLAC file does not start with STREAMINFO".to_string())),
+        [b'I', b'D', b'3', ..] | [0xFF, _, ..] | [b'O', b'g', b'g', b'S', ..] => {
+            Err(MelError::UnsupportedFormat("probe_audio reads WAV and FLAC headers only".to_string()))
+        }
+        _ => {
+            let reader = WavReader::open(path)?;
+            let spec = reader.spec();
+            Ok(AudioFileInfo {
+                sample_rate: spec.sample_rate,
+                channels: spec.channels,
+                bits_per_sample: spec.bits_per_sample,
+                duration_secs: reader.duration() as f64 / spec.sample_rate as f64,
+                format: AudioFormat::Wav,
+            })
+        }
+    }
+}
+
+/// Synthesises `text` with the `espeak-ng` command and returns it as mono audio resampled to
+/// `sample_rate`.
+#[cfg(feature = "test-utils")]
+pub fn generate_tts_audio(text: &str, sample_rate: u32) -> Result<Vec<f32>, MelError> {
+    use std::process::{Command, Stdio};
+
+    let mut child = Command::new("espeak-ng")
+        .arg("--stdout")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(MelError::InvalidAudio(format!(
+            "espeak-ng failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    // espeak-ng cannot seek back into a pipe, so its RIFF and data sizes are placeholders.
+    let mut wav = output.stdout;
+    let data_pos = wav.windows(4).skip(12).position(|id| id == b"data").map(|pos| pos + 12);
+    match data_pos {
+        Some(pos) if wav.len() >= pos + 8 => {
+            let riff_len = (wav.len() - 8) as u32;
+            let data_len = (wav.len() - pos - 8) as u32;
+            wav[4..8].copy_from_slice(&riff_len.to_le_bytes());
+            wav[pos + 4..pos + 8].copy_from_slice(&data_len.to_le_bytes());
+        }
+        _ => return Err(MelError::InvalidAudio("espeak-ng produced no WAV data".to_string())),
+    }
+
+    let (mono, orig_sample_rate) = read_wav_bytes(&wav, &DownmixStrategy::Average)?;
+    let config = MelConfig { sample_rate, ..MelConfig::whisper_default() };
+    resample_audio(mono, orig_sample_rate, &config)
+}
+
+/// Decodes the file at `path` with the reader matching its leading magic bytes, falling back to
+/// WAV.
+#[cfg(all(feature = "std", not(feature = "wasm")))]
+fn read_audio(path: &str, strategy: &DownmixStrategy) -> Result<(Vec<f32>, u32), MelError> {
+    let mut magic = Vec::with_capacity(4);
+    File::open(path)?.take(4).read_to_end(&mut magic)?;
+    match magic.as_slice() {
+        #[cfg(feature = "flac")]
+        b"fLaC" => read_flac(path, strategy),
+        #[cfg(not(feature = "flac"))]
+        b"fLaC" => Err(MelError::UnsupportedFormat("FLAC input requires the `flac` feature".to_string())),
+        #[cfg(feature = "mp3")]
+        [b'I', b'D', b'3', ..] => read_mp3(path, strategy),
+        #[cfg(feature = "mp3")]
+        [0xFF, sync, ..] if sync & 0xE0 == 0xE0 => read_mp3(path, strategy),
+        #[cfg(not(feature = "mp3"))]
+        [b'I', b'D', b'3', ..] => Err(MelError::UnsupportedFormat("MP3 input requires the `mp3` feature".to_string())),
+        #[cfg(feature = "ogg")]
+        b"OggS" => read_ogg(path, strategy),
+        #[cfg(not(feature = "ogg"))]
+        b"OggS" => Err(MelError::UnsupportedFormat("Ogg input requires the `ogg` feature".to_string())),
+        _ => read_wav(path, strategy),
+    }
+}
+
+#[cfg(all(feature = "std", not(feature = "wasm")))]
+fn read_wav(path: &str, strategy: &DownmixStrategy) -> Result<(Vec<f32>, u32), MelError> {
+    decode_wav(WavReader::open(path)?, strategy)
+}
+
+fn read_wav_bytes(bytes: &[u8], strategy: &DownmixStrategy) -> Result<(Vec<f32>, u32), MelError> {
+    decode_wav(WavReader::new(Cursor::new(bytes))?, strategy)
+}
+
+fn decode_wav<R: Read>(mut reader: WavReader<R>, strategy: &DownmixStrategy) -> Result<(Vec<f32>, u32), MelError> {
+    let spec = reader.spec();
+    let samples = read_wav_samples(&mut reader, usize::MAX)?;
+    Ok((downmix(samples, spec.channels, strategy)?, spec.sample_rate))
+}
+
+#[cfg(all(feature = "flac", not(feature = "wasm")))]
+fn read_flac(path: &str, strategy: &DownmixStrategy) -> Result<(Vec<f32>, u32), MelError> {
+    let mut reader = claxon::FlacReader::open(path)?;
+    let info = reader.streaminfo();
+    let scale = ((1i64 << (info.bits_per_sample - 1)) - 1) as f32;
+    let samples = reader
+        .samples()
+        .map(|s| s.map(|s| s as f32 / scale))
+        .collect::<Result<_, _>>()?;
+    Ok((downmix(samples, info.channels as u16, strategy)?, info.sample_rate))
+}
+
+/// Samples of decoder delay at the start of every MP3 stream.
+#[cfg(feature = "mp3")]
+const MP3_DECODER_DELAY: usize = 576;
+
+#[cfg(all(feature = "mp3", not(feature = "wasm")))]
+fn read_mp3(path: &str, strategy: &DownmixStrategy) -> Result<(Vec<f32>, u32), MelError> {
+    let mut decoder = minimp3::Decoder::new(BufReader::new(File::open(path)?));
+    let mut samples = Vec::new();
+    let mut format = None;
+    loop {
+        match decoder.next_frame() {
+            Ok(frame) => {
+                let frame_format = (frame.sample_rate as u32, frame.channels as u16);
+                if *format.get_or_insert(frame_format) != frame_format {
+                    return Err(MelError::UnsupportedFormat(
+                        "MP3 streams that change sample rate or channel count are not supported".to_string(),
+                    ));
+                }
+                samples.extend(frame.data.iter().map(|&s| s as f32 / i16::MAX as f32));
+            }
+            Err(minimp3::Error::Eof) => break,
+            Err(minimp3::Error::SkippedData) => continue,
+            Err(minimp3::Error::Io(e)) => return Err(MelError::IoError(e)),
+            Err(minimp3::Error::InsufficientData) => {
+                return Err(MelError::InvalidAudio("Truncated MP3 frame".to_string()));
+            }
+        }
+    }
+
+    let Some((sample_rate, channels)) = format else {
+        return Err(MelError::InvalidAudio("MP3 stream contains no audio frames".to_string()));
+    };
+    let delay = (MP3_DECODER_DELAY * channels as usize).min(samples.len());
+    samples.drain(..delay);
+    Ok((downmix(samples, channels, strategy)?, sample_rate))
+}
+
+#[cfg(all(feature = "ogg", not(feature = "wasm")))]
+fn read_ogg(path: &str, strategy: &DownmixStrategy) -> Result<(Vec<f32>, u32), MelError> {
+    // The first Ogg page carries the codec's identification packet right after its segment table.
+    let mut first_page = Vec::with_capacity(512);
+    File::open(path)?.take(512).read_to_end(&mut first_page)?;
+    let packet = first_page
+        .get(26)
+        .and_then(|&n_segments| first_page.get(27 + n_segments as usize..))
+        .unwrap_or_default();
+    if packet.starts_with(b"OpusHead") {
+        return Err(MelError::UnsupportedFormat("Ogg container holds Opus audio; only Vorbis is supported".to_string()));
+    }
+    if packet.starts_with(b"\x7FFLAC") {
+        return Err(MelError::UnsupportedFormat("Ogg container holds FLAC audio; only Vorbis is supported".to_string()));
+    }
+
+    let mut reader = lewton::inside_ogg::OggStreamReader::new(BufReader::new(File::open(path)?))?;
+    let channels = reader.ident_hdr.audio_channels as u16;
+    let sample_rate = reader.ident_hdr.audio_sample_rate;
+    let mut samples = Vec::new();
+    while let Some(packet) = reader.read_dec_packet_itl()? {
+        samples.extend(packet.iter().map(|&s| s as f32 / i16::MAX as f32));
+    }
+    Ok((downmix(samples, channels, strategy)?, sample_rate))
+}
+
+/// Reads up to `limit` interleaved samples from the reader's current position, normalized to
+/// [-1.0, 1.0].
+fn read_wav_samples<R: Read>(reader: &mut WavReader<R>, limit: usize) -> Result<Vec<f32>, MelError> {
+    let spec = reader.spec();
+    let samples = match (spec.sample_format, spec.bits_per_sample) {
+        (SampleFormat::Int, bits @ 1..=16) => {
+            let scale = ((1i32 << (bits - 1)) - 1) as f32;
+            reader
+                .samples::<i16>()
+                .take(limit)
+                .map(|s| s.map(|s| s as f32 / scale))
+                .collect::<Result<_, _>>()?
+        }
+        (SampleFormat::Int, bits @ 17..=32) => {
+            let scale = ((1i64 << (bits - 1)) - 1) as f32;
+            reader
+                .samples::<i32>()
+                .take(limit)
+                .map(|s| s.map(|s| s as f32 / scale))
+                .collect::<Result<_, _>>()?
+        }
+        (SampleFormat::Float, 32) => reader
+            .samples::<f32>()
+            .take(limit)
+            .map(|s| s.map(|s| s.clamp(-1.0, 1.0)))
+            .collect::<Result<_, _>>()?,
+        (format, bits) => {
+            return Err(MelError::UnsupportedFormat(format!(
+                "{}-bit {:?} WAV samples are not supported",
+                bits, format
+            )));
+        }
+    };
+    Ok(samples)
+}
+
+fn downmix(samples: Vec<f32>, channels: u16, strategy: &DownmixStrategy) -> Result<Vec<f32>, MelError> {
+    match strategy {
+        DownmixStrategy::Average => {
+            if channels == 1 {
+                return Ok(samples);
+            }
+            Ok(samples
+                .chunks(channels as usize)
+                .map(|chunk| chunk.iter().sum::<f32>() / chunk.len() as f32)
+                .collect())
+        }
+        DownmixStrategy::SelectChannel(channel) => {
+            if *channel >= channels {
+                return Err(MelError::InvalidConfig(format!(
+                    "Channel {} requested but the audio has {} channel(s)",
+                    channel, channels
+                )));
+            }
+            Ok(samples
+                .chunks(channels as usize)
+                .filter_map(|chunk| chunk.get(*channel as usize).copied())
+                .collect())
+        }
+        DownmixStrategy::WeightedSum(weights) => {
+            if weights.len() != channels as usize {
+                return Err(MelError::InvalidConfig(format!(
+                    "{} downmix weights given for {} channel(s)",
+                    weights.len(), channels
+                )));
+            }
+            Ok(samples
+                .chunks(channels as usize)
+                .map(|chunk| chunk.iter().zip(weights).map(|(s, w)| s * w).sum())
+                .collect())
+        }
+    }
+}
+
+fn apply_preemphasis(mut samples: Vec<f32>, alpha: f32) -> Result<Vec<f32>, MelError> {
+    for i in (1..samples.len()).rev() {
+        samples[i] -= alpha * samples[i - 1];
+    }
+    Ok(samples)
+}
+
+fn resample_audio(samples: Vec<f32>, orig_rate: u32, config: &MelConfig) -> Result<Vec<f32>, MelError> {
+    let target_rate = config.sample_rate;
+    if orig_rate == target_rate {
+        return Ok(samples)
+    }
+
+    let ratio = target_rate as f64 / orig_rate as f64;
+    resample_by_ratio(samples, ratio, config.resampling_quality)
+}
+
+/// Resamples `samples` to `ratio` times as many in one pass.
+fn resample_by_ratio(samples: Vec<f32>, ratio: f64, quality: ResamplingQuality) -> Result<Vec<f32>, MelError> {
+    let mut resampler = SincFixedIn::<f32>::new(ratio, 1.0, sinc_parameters(quality), samples.len(), 1)?;
+    let outputs = resampler.process(&[samples], None)?;
+    Ok(outputs.into_iter().next().unwrap())
+}
+
+fn sinc_parameters(quality: ResamplingQuality) -> SincInterpolationParameters {
+    // rubato's sharpest window is BlackmanHarris2; Best gets its extra quality from the longer
+    // sinc. The shorter Fast filter needs a lower cutoff to keep its transition band below Nyquist.
+    let (sinc_len, oversampling_factor, interpolation) = match quality {
+        ResamplingQuality::Fast => (64, 128, SincInterpolationType::Linear),
+        ResamplingQuality::Medium => (256, 256, SincInterpolationType::Cubic),
+        ResamplingQuality::Best => (512, 512, SincInterpolationType::Cubic),
+    };
+    let window = WindowFunction::BlackmanHarris2;
+    let f_cutoff = match quality {
+        ResamplingQuality::Medium => 0.95,
+        _ => calculate_cutoff(sinc_len, window),
+    };
+    SincInterpolationParameters {
+        sinc_len,
+        f_cutoff,
+        oversampling_factor,
+        interpolation,
+        window,
+    }
+}
+
+/// Output samples produced per call to the streaming resampler.
+const STREAMING_RESAMPLER_CHUNK: usize = 1024;
+
+/// Carries a [`SincFixedOut`] resampler and the input it has not consumed yet between calls to
+/// [`resample_audio_streaming`].
+#[derive(Default)]
+pub struct ResamplerState {
+    resampler: Option<(SincFixedOut<f32>, u32, u32)>,
+    pending: Vec<f32>,
+}
+
+impl ResamplerState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resamples whatever input is still buffered, zero-padding the final chunk, and resets the
+    /// state for a new stream.
+    pub fn flush(&mut self) -> Result<Vec<f32>, MelError> {
+        let pending = std::mem::take(&mut self.pending);
+        match self.resampler.take() {
+            Some((mut resampler, _, _)) if !pending.is_empty() => {
+                let outputs = resampler.process_partial(Some(&[pending]), None)?;
+                Ok(outputs.into_iter().next().unwrap_or_default())
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+}
+
+/// Resamples one chunk of a stream whose total length is unknown. Input that does not fill a
+/// whole resampler chunk is kept in `state` until the next call or [`ResamplerState::flush`];
+/// as with any sinc resampler, the output starts delayed by the filter's latency. Changing the
+/// rates mid-stream starts a new resampler and drops the buffered input.
+pub fn resample_audio_streaming(
+    chunk: &[f32],
+    state: &mut ResamplerState,
+    orig_rate: u32,
+    target_rate: u32,
+) -> Result<Vec<f32>, MelError> {
+    if orig_rate == target_rate {
+        return Ok(chunk.to_vec());
+    }
+    if orig_rate == 0 || target_rate == 0 {
+        return Err(MelError::InvalidConfig(format!(
+            "Sample rates must be positive, got {} and {}",
+            orig_rate, target_rate
+        )));
+    }
+
+    let rates_changed = !matches!(&state.resampler, Some((_, from, to)) if *from == orig_rate && *to == target_rate);
+    if rates_changed {
+        let ratio = target_rate as f64 / orig_rate as f64;
+        let resampler = SincFixedOut::<f32>::new(ratio, 1.0, sinc_parameters(ResamplingQuality::Medium), STREAMING_RESAMPLER_CHUNK, 1)?;
+        state.resampler = Some((resampler, orig_rate, target_rate));
+        state.pending.clear();
+    }
+    let Some((resampler, _, _)) = state.resampler.as_mut() else {
+        return Ok(Vec::new());
+    };
+
+    state.pending.extend_from_slice(chunk);
+    let mut output = Vec::new();
+    let mut consumed = 0;
+    while state.pending.len() - consumed >= resampler.input_frames_next() {
+        let needed = resampler.input_frames_next();
+        let outputs = resampler.process(&[&state.pending[consumed..consumed + needed]], None)?;
+        output.extend_from_slice(&outputs[0]);
+        consumed += needed;
+    }
+    state.pending.drain(..consumed);
+    Ok(output)
+}
+
+/// Cuts the leading and trailing frames whose power is more than `top_db` below the loudest
+/// frame, returning the kept samples with their `start..end` range in `samples`. All-silent or
+/// empty input yields no samples and `(0, 0)`.
+pub fn trim_silence(samples: &[f32], top_db: f32, frame_length: usize, hop_length: usize) -> (Vec<f32>, usize, usize) {
+    let hop_length = hop_length.max(1);
+    let n_frames = samples.len().saturating_sub(frame_length).div_ceil(hop_length) + 1;
+    let power: Vec<f32> = (0..n_frames)
+        .map(|i| {
+            let start = (i * hop_length).min(samples.len());
+            mean_square(&samples[start..(start + frame_length).min(samples.len())])
+        })
+        .collect();
+
+    let max_power = power.iter().copied().fold(0.0f32, f32::max);
+    if max_power <= 0.0 {
+        return (Vec::new(), 0, 0);
+    }
+    let threshold = max_power * 10f32.powf(-top_db / 10.0);
+    let loud = |p: &f32| *p > threshold;
+    let first = power.iter().position(loud).unwrap_or(0);
+    let last = power.iter().rposition(loud).unwrap_or(first);
+
+    let start = first * hop_length;
+    let end = (last * hop_length + frame_length).min(samples.len());
+    (samples[start..end].to_vec(), start, end)
+}
+
+/// Shifts the pitch of `samples` by `semitones`, which may be fractional, keeping their length.
+/// The audio is time-stretched by the phase vocoder, then resampled back to its original
+/// duration.
+pub fn pitch_shift(samples: &[f32], sample_rate: u32, semitones: f32) -> Result<Vec<f32>, MelError> {
+    if !semitones.is_finite() {
+        return Err(MelError::InvalidConfig(format!("Pitch shift ({} semitones) must be finite", semitones)));
+    }
+    if sample_rate == 0 {
+        return Err(MelError::InvalidConfig("sample_rate must be positive".to_string()));
+    }
+    if semitones == 0.0 || samples.is_empty() {
+        return Ok(samples.to_vec());
+    }
+
+    let rate = 2f64.powf(-semitones as f64 / 12.0);
+    let n_fft = vocoder_frame_length(sample_rate);
+    let stretched = phase_vocoder(samples, rate, n_fft, n_fft / 4)?;
+    let mut shifted = resample_by_ratio(stretched, rate, ResamplingQuality::Medium)?;
+    shifted.resize(samples.len(), 0.0);
+    Ok(shifted)
+}
+
+/// Plays `samples` `rate` times as fast without changing their pitch, returning about
+/// `len / rate` samples. `n_fft` is the frame and FFT size of the phase vocoder's Hann STFT;
+/// a `hop_length` of `n_fft / 4` or less keeps the resynthesis smooth.
+pub fn time_stretch(samples: &[f32], rate: f32, n_fft: usize, hop_length: usize) -> Result<Vec<f32>, MelError> {
+    if !rate.is_finite() || rate <= 0.0 {
+        return Err(MelError::InvalidConfig(format!("Time stretch rate ({}) must be positive", rate)));
+    }
+    if n_fft < 2 || hop_length == 0 || hop_length > n_fft {
+        return Err(MelError::InvalidConfig(format!(
+            "Time stretch needs n_fft >= 2 and 0 < hop_length <= n_fft, got {} and {}",
+            n_fft, hop_length
+        )));
+    }
+    phase_vocoder(samples, rate as f64, n_fft, hop_length)
+}
+
+/// Phase vocoder frame size for `sample_rate`: about 64 ms, rounded up to a power of two.
+fn vocoder_frame_length(sample_rate: u32) -> usize {
+    (sample_rate as usize / 16).next_power_of_two()
+}
+
+/// Plays `samples` `rate` times as fast without changing their pitch: a centred Hann STFT is
+/// read at fractional frame steps of `rate`, interpolating magnitudes and advancing each bin's
+/// phase by its measured instantaneous frequency, then resynthesised by overlap-add.
+fn phase_vocoder(samples: &[f32], rate: f64, n_fft: usize, hop_length: usize) -> Result<Vec<f32>, MelError> {
+    let pad = n_fft / 2;
+    let mut padded = vec![0.0; pad];
+    padded.extend_from_slice(samples);
+    padded.resize(padded.len() + pad, 0.0);
+
+    let window = make_window(n_fft, WindowType::Hann);
+    let mut planner = RealFftPlanner::<f32>::new();
+    let r2c = planner.plan_fft_forward(n_fft);
+    let c2r = planner.plan_fft_inverse(n_fft);
+    let frames = frame_signal_views(&padded, n_fft, hop_length).map(<[f32]>::to_vec).collect();
+    let stft = apply_rfft(apply_window(frames, &window)?, r2c.as_ref())?;
+    let Some(first) = stft.first() else {
+        return Ok(Vec::new());
+    };
+
+    let tau = 2.0 * std::f32::consts::PI;
+    let silence = vec![Complex::new(0.0, 0.0); first.len()];
+    let mut phase: Vec<f32> = first.iter().map(|bin| bin.arg()).collect();
+    let mut stretched = Vec::new();
+    let mut position = 0.0f64;
+    while position < stft.len() as f64 {
+        let t = position as usize;
+        let alpha = (position - t as f64) as f32;
+        let (current, next) = (&stft[t], stft.get(t + 1).unwrap_or(&silence));
+        let frame = current.iter()
+            .zip(next)
+            .zip(phase.iter_mut())
+            .enumerate()
+            .map(|(k, ((a, b), phase))| {
+                let bin = Complex::from_polar((1.0 - alpha) * a.norm() + alpha * b.norm(), *phase);
+                // Expected advance of bin k over one hop, plus the deviation measured between frames.
+                let advance = tau * k as f32 * hop_length as f32 / n_fft as f32;
+                let deviation = b.arg() - a.arg() - advance;
+                *phase = (*phase + advance + deviation - tau * (deviation / tau).round()).rem_euclid(tau);
+                bin
+            })
+            .collect();
+        stretched.push(frame);
+        position += rate;
+    }
+
+    let len = (samples.len() as f64 / rate).round() as usize;
+    let mut output: Vec<f32> = overlap_add(&stretched, &window, hop_length, c2r.as_ref())?
+        .into_iter()
+        .skip(pad)
+        .take(len)
+        .collect();
+    output.resize(len, 0.0);
+    Ok(output)
+}
+
+/// Pole frequencies in Hz of the analog A-weighting curve (IEC 61672-1).
+const A_WEIGHTING_POLES: [f64; 4] = [20.598997, 107.65265, 737.86223, 12194.217];
+
+/// Filters `samples` with the A-weighting curve, normalised to unit gain at 1 kHz. The analog
+/// transfer function is mapped to `sample_rate` with the bilinear transform and run as a cascade
+/// of three biquads, so the response drops off faster than the analog curve near Nyquist.
+///
+/// Returns `InvalidConfig` unless 1 kHz lies below Nyquist, since the gain is normalised there.
+pub fn apply_a_weighting(samples: &[f32], sample_rate: u32) -> Result<Vec<f32>, MelError> {
+    if sample_rate <= 2000 {
+        return Err(MelError::InvalidConfig(format!(
+            "A-weighting needs a sample rate above 2000 Hz to normalise at 1 kHz, got {}",
+            sample_rate
+        )));
+    }
+    let fs2 = 2.0 * sample_rate as f64;
+    let [p1, p2, p3, p4] = A_WEIGHTING_POLES
+        .map(|f| 2.0 * std::f64::consts::PI * f)
+        .map(|w| (fs2 - w) / (fs2 + w));
+    // Four zeros at DC; the two poles the analog curve has in excess become zeros at Nyquist.
+    let sections = [
+        ([1.0, -2.0, 1.0], [1.0, -2.0 * p1, p1 * p1]),
+        ([1.0, -2.0, 1.0], [1.0, -2.0 * p4, p4 * p4]),
+        ([1.0, 2.0, 1.0], [1.0, -(p2 + p3), p2 * p3]),
+    ];
+
+    let z = Complex::from_polar(1.0, 2.0 * std::f64::consts::PI * 1000.0 / sample_rate as f64);
+    let response = |c: &[f64; 3]| c[0] + c[1] / z + c[2] / (z * z);
+    let gain_at_1khz: f64 = sections.iter().map(|(b, a)| (response(b) / response(a)).norm()).product();
+
+    let mut output: Vec<f64> = samples.iter().map(|&x| x as f64 / gain_at_1khz).collect();
+    for (b, a) in &sections {
+        let (mut s1, mut s2) = (0.0, 0.0);
+        for x in output.iter_mut() {
+            let y = b[0] * *x + s1;
+            s1 = b[1] * *x - a[1] * y + s2;
+            s2 = b[2] * *x - a[2] * y;
+            *x = y;
+        }
+    }
+    Ok(output.into_iter().map(|y| y as f32).collect())
+}
+
+/// Drops the windows of `samples` whose short-time energy marks them as silence.
+pub fn apply_vad(samples: &[f32], config: &VadConfig) -> Vec<f32> {
+    if config.window_length == 0 {
+        return samples.to_vec();
+    }
+
+    let is_speech: Vec<bool> = samples
+        .chunks(config.window_length)
+        .map(|window| {
+            let energy = window.iter().map(|x| x * x).sum::<f32>() / window.len() as f32;
+            10.0 * (energy + 1e-10).log10() >= config.energy_threshold_db
+        })
+        .collect();
+
+    let mut keep = vec![false; is_speech.len()];
+    let mut start = 0;
+    while start < is_speech.len() {
+        if !is_speech[start] {
+            start += 1;
+            continue;
+        }
+        let end = is_speech[start..].iter().position(|&speech| !speech).map_or(is_speech.len(), |len| start + len);
+        if end - start >= config.min_speech_frames {
+            let hangover_end = (end + config.hangover_frames).min(keep.len());
+            keep[start..hangover_end].fill(true);
+        }
+        start = end;
+    }
+
+    samples
+        .chunks(config.window_length)
+        .zip(keep)
+        .filter(|(_, keep)| *keep)
+        .flat_map(|(window, _)| window.iter().copied())
+        .collect()
+}
+
+fn normalize(mut samples: Vec<f32>, mode: NormalizationMode) -> Result<Vec<f32>, MelError> {
+    if samples.is_empty() {
+        return Ok(samples);
+    }
+    let len = samples.len() as f32;
+
+    let (offset, scale) = match mode {
+        NormalizationMode::None | NormalizationMode::Pcen(_) => return Ok(samples),
+        NormalizationMode::MeanVariance => {
+            let mean = samples.iter().sum::<f32>() / len;
+            let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f32>() / len;
+            (mean, variance.sqrt())
+        }
+        NormalizationMode::Peak => (0.0, samples.iter().fold(0.0f32, |peak, x| peak.max(x.abs()))),
+        NormalizationMode::Rms => (0.0, (samples.iter().map(|x| x * x).sum::<f32>() / len).sqrt()),
+    };
+
+    // Silent input has nothing to rescale.
+    let scale = if scale > 1e-10 { scale } else { 1.0 };
+    for sample in samples.iter_mut() {
+        *sample = (*sample - offset) / scale;
+    }
+    Ok(samples)
+}
+
+fn pad_or_truncate(mut samples: Vec<f32>, config: &MelConfig) -> Result<Vec<f32>, MelError> {
+    if let Some(target_len) = config.max_samples() {
+        samples.resize(target_len, 0.0);
+    }
+
+    let pad_each = config.frame_length / 2;
+    let n = samples.len();
+    let edge_sample = |i: isize| -> f32 {
+        if n == 0 {
+            return 0.0;
+        }
+        match config.padding_mode {
+            PaddingMode::Zero => 0.0,
+            PaddingMode::Reflect => samples[reflect_index(i, n)],
+            PaddingMode::Wrap => samples[i.rem_euclid(n as isize) as usize],
+            PaddingMode::Edge => samples[i.clamp(0, n as isize - 1) as usize],
+        }
+    };
+
+    let left: Vec<f32> = (-(pad_each as isize)..0).map(edge_sample).collect();
+    let right: Vec<f32> = (n as isize..(n + pad_each) as isize).map(edge_sample).collect();
+
+    let mut out = Vec::with_capacity(pad_each + n + pad_each);
+    out.extend(left);
+    out.extend(samples);
+    out.extend(right);
+    Ok(out)
+}
+
+/// Per-frame `log10(mean(x^2) + 1e-10)`.
+pub fn compute_log_energy(frames: &[Vec<f32>]) -> Vec<f32> {
+    frames.iter()
+        .map(|frame| (mean_square(frame) + 1e-10).log10())
+        .collect()
+}
+
+/// Per-frame root-mean-square amplitude.
+pub fn compute_rms_energy(frames: &[Vec<f32>]) -> Vec<f32> {
+    frames.iter()
+        .map(|frame| mean_square(frame).sqrt())
+        .collect()
+}
+
+/// Per-frame fraction of adjacent sample pairs whose sign differs: 0 for a constant signal,
+/// 1 for a signal alternating sign on every sample.
+pub fn zero_crossing_rate(frames: &[Vec<f32>]) -> Vec<f32> {
+    fn sign(x: f32) -> f32 {
+        if x > 0.0 {
+            1.0
+        } else if x < 0.0 {
+            -1.0
+        } else {
+            0.0
+        }
+    }
+
+    frames.iter()
+        .map(|frame| {
+            if frame.len() < 2 {
+                return 0.0;
+            }
+            let crossings: f32 = frame.windows(2)
+                .map(|pair| (sign(pair[1]) - sign(pair[0])).abs())
+                .sum();
+            crossings / (2.0 * (frame.len() - 1) as f32)
+        })
+        .collect()
+}
+
+fn mean_square(frame: &[f32]) -> f32 {
+    if frame.is_empty() {
+        return 0.0;
+    }
+    frame.iter().map(|x| x * x).sum::<f32>() / frame.len() as f32
+}
+
+/// Per-frame magnitude-weighted mean frequency, `sum(f * |X[f]|) / sum(|X[f]|)`, from a power
+/// spectrogram. Silent frames yield 0.
+pub fn spectral_centroid(power_spec: &[Vec<f32>], fft_freqs: &[f32]) -> Vec<f32> {
+    power_spec.iter()
+        .map(|frame| {
+            let mut weighted = 0.0;
+            let mut total = 0.0;
+            for (power, freq) in frame.iter().zip(fft_freqs) {
+                let magnitude = power.sqrt();
+                weighted += freq * magnitude;
+                total += magnitude;
+            }
+            if total > 0.0 { weighted / total } else { 0.0 }
+        })
+        .collect()
+}
+
+const MIN_F0: f32 = 50.0;
+const MAX_F0: f32 = 500.0;
+const VOICING_THRESHOLD: f32 = 0.5;
+const OCTAVE_TOLERANCE: f32 = 0.9;
+
+/// Per-frame pitch from the normalised autocorrelation peak over lags
+/// `[sample_rate / max_f0, sample_rate / min_f0]`, preferring the shortest lag within 10% of the
+/// maximum to avoid octave errors. Frames whose peak correlation is below 0.5 are unvoiced and
+/// yield `None`.
+pub fn estimate_f0(frames: &[Vec<f32>], sample_rate: u32, min_f0: f32, max_f0: f32) -> Vec<Option<f32>> {
+    let min_lag = (sample_rate as f32 / max_f0).floor().max(1.0) as usize;
+    let max_lag = (sample_rate as f32 / min_f0).ceil() as usize;
+
+    frames.iter()
+        .map(|frame| {
+            let max_lag = max_lag.min(frame.len().saturating_sub(1));
+            if min_lag > max_lag {
+                return None;
+            }
+            let correlation = |lag: usize| {
+                let (head, tail) = (&frame[..frame.len() - lag], &frame[lag..]);
+                let energy = head.iter().map(|x| x * x).sum::<f32>() * tail.iter().map(|x| x * x).sum::<f32>();
+                if energy > 0.0 { dot(head, tail) / energy.sqrt() } else { 0.0 }
+            };
+            let correlations: Vec<f32> = (min_lag..=max_lag).map(correlation).collect();
+            let global_peak = correlations.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+            if global_peak < VOICING_THRESHOLD {
+                return None;
+            }
+            // Every multiple of the period correlates almost as well as the period itself, so
+            // take the shortest lag that peaks within `OCTAVE_TOLERANCE` of the best one.
+            let is_local_peak = |i: usize| {
+                (i == 0 || correlations[i - 1] <= correlations[i])
+                    && (i + 1 == correlations.len() || correlations[i + 1] <= correlations[i])
+            };
+            let best = (0..correlations.len())
+                .find(|&i| correlations[i] >= OCTAVE_TOLERANCE * global_peak && is_local_peak(i))?;
+            let peak = correlations[best];
+
+            // Parabolic interpolation between the neighbouring lags refines the period.
+            let mut lag = (min_lag + best) as f32;
+            if best > 0 && best + 1 < correlations.len() {
+                let (left, right) = (correlations[best - 1], correlations[best + 1]);
+                let curvature = left - 2.0 * peak + right;
+                if curvature < 0.0 {
+                    lag += 0.5 * (left - right) / curvature;
+                }
+            }
+            Some(sample_rate as f32 / lag)
+        })
+        .collect()
+}
+
+/// Pitch of `frame` by YIN (de Cheveigné & Kawahara, 2002): the first lag in
+/// `[sample_rate / fmax, sample_rate / fmin]` whose cumulative mean normalised difference drops
+/// below `threshold`, followed down to its local minimum and refined by parabolic
+/// interpolation. Lags are limited to half the frame. Returns `None` for unvoiced frames and
+/// for an empty search range.
+pub fn yin_pitch(frame: &[f32], sample_rate: u32, fmin: f32, fmax: f32, threshold: f32) -> Option<f32> {
+    if !(fmin > 0.0 && fmax > fmin) {
+        return None;
+    }
+    let min_lag = (sample_rate as f32 / fmax).floor().max(1.0) as usize;
+    let max_lag = ((sample_rate as f32 / fmin).ceil() as usize).min(frame.len() / 2);
+    if min_lag >= max_lag {
+        return None;
+    }
+
+    // Difference function over a fixed integration window, then its cumulative mean
+    // normalisation, which is 1 at lag 0 by definition.
+    let window = frame.len() - max_lag;
+    let mut cmnd = vec![1.0f32; max_lag + 1];
+    let mut running_sum = 0.0f32;
+    for lag in 1..=max_lag {
+        let difference: f32 = frame[..window].iter()
+            .zip(&frame[lag..lag + window])
+            .map(|(x, y)| (x - y) * (x - y))
+            .sum();
+        running_sum += difference;
+        cmnd[lag] = if running_sum > 0.0 { difference * lag as f32 / running_sum } else { 1.0 };
+    }
+
+    let mut lag = (min_lag..=max_lag).find(|&lag| cmnd[lag] < threshold)?;
+    while lag < max_lag && cmnd[lag + 1] < cmnd[lag] {
+        lag += 1;
+    }
+
+    let mut period = lag as f32;
+    if lag > min_lag && lag < max_lag {
+        let (left, centre, right) = (cmnd[lag - 1], cmnd[lag], cmnd[lag + 1]);
+        let curvature = left - 2.0 * centre + right;
+        if curvature > 0.0 {
+            period += 0.5 * (left - right) / curvature;
+        }
+    }
+    Some(sample_rate as f32 / period)
+}
+
+/// [`yin_pitch`] of every frame with the range and threshold of `config`.
+pub fn extract_pitch_track(frames: &[Vec<f32>], sample_rate: u32, config: &PitchConfig) -> Vec<Option<f32>> {
+    frames.iter()
+        .map(|frame| yin_pitch(frame, sample_rate, config.fmin, config.fmax, config.threshold))
+        .collect()
+}
+
+/// Chroma filter bank as `n_chroma` rows of `n_fft` rfft-bin weights, following librosa's
+/// `filters.chroma`: Gaussian bumps around each pitch class, L2-normalised per FFT bin and
+/// weighted towards the octaves around C5. Rows are rolled by `3 * (n_chroma / 12)` as librosa
+/// does, so row 0 is C whenever `n_chroma` is a multiple of 12. `tuning` is the deviation from
+/// A440 in fractions of a chroma bin.
+pub fn make_chroma_filterbank(n_fft: usize, sample_rate: u32, n_chroma: usize, tuning: f32) -> Vec<Vec<f32>> {
+    const CENTER_OCTAVE: f32 = 5.0;
+    const OCTAVE_WIDTH: f32 = 2.0;
+
+    if n_fft < 2 || n_chroma == 0 {
+        return vec![vec![0.0; n_fft]; n_chroma];
+    }
+    let fft_len = 2 * (n_fft - 1);
+    let n_chroma_f = n_chroma as f32;
+    let a440 = 440.0 * 2f32.powf(tuning / n_chroma_f);
+    let c1 = a440 / 16.0;
+
+    // Fractional chroma position of every FFT bin; DC is placed 1.5 octaves below bin 1.
+    let mut bin_positions: Vec<f32> = (1..fft_len)
+        .map(|k| n_chroma_f * (k as f32 * sample_rate as f32 / fft_len as f32 / c1).log2())
+        .collect();
+    bin_positions.insert(0, bin_positions[0] - 1.5 * n_chroma_f);
+    let mut bin_widths: Vec<f32> = bin_positions.windows(2).map(|pair| (pair[1] - pair[0]).max(1.0)).collect();
+    bin_widths.push(1.0);
+
+    let half = (n_chroma_f / 2.0).round();
+    let mut weights: Vec<Vec<f32>> = (0..n_chroma)
+        .map(|c| {
+            bin_positions.iter()
+                .zip(&bin_widths)
+                .map(|(&position, &width)| {
+                    let distance = (position - c as f32 + half + 10.0 * n_chroma_f).rem_euclid(n_chroma_f) - half;
+                    (-0.5 * (2.0 * distance / width).powi(2)).exp()
+                })
+                .collect()
+        })
+        .collect();
+
+    for k in 0..fft_len {
+        let norm = weights.iter().map(|row| row[k] * row[k]).sum::<f32>().sqrt();
+        let octave = (-0.5 * ((bin_positions[k] / n_chroma_f - CENTER_OCTAVE) / OCTAVE_WIDTH).powi(2)).exp();
+        for row in weights.iter_mut() {
+            row[k] = if norm > 0.0 { row[k] / norm } else { row[k] } * octave;
+        }
+    }
+
+    // Rotate so that row 0 is C rather than A, rounding the shift down like librosa.
+    weights.rotate_left(3 * (n_chroma / 12));
+    for row in weights.iter_mut() {
+        row.truncate(n_fft);
+    }
+    weights
+}
+
+/// Projects a `[n_frames][n_fft]` power spectrogram onto `n_chroma` pitch classes and scales
+/// each frame so its largest bin is 1, as librosa's `chroma_stft` does.
+pub fn chroma_stft(power_spec: &[Vec<f32>], sample_rate: u32, n_chroma: usize, tuning: f32) -> Result<Vec<Vec<f32>>, MelError> {
+    if n_chroma == 0 {
+        return Err(MelError::InvalidConfig("n_chroma must be greater than 0".to_string()));
+    }
+    let n_fft = power_spec.first().map_or(0, Vec::len);
+    if n_fft < 2 || power_spec.iter().any(|frame| frame.len() != n_fft) {
+        return Err(MelError::InvalidConfig("Power spectrogram frames must share at least 2 bins".to_string()));
+    }
+
+    let filterbank = make_chroma_filterbank(n_fft, sample_rate, n_chroma, tuning);
+    Ok(power_spec.iter()
+        .map(|frame| {
+            let mut chroma: Vec<f32> = filterbank.iter().map(|row| dot(row, frame)).collect();
+            let peak = chroma.iter().fold(0.0f32, |peak, &v| peak.max(v.abs()));
+            if peak > 0.0 {
+                chroma.iter_mut().for_each(|v| *v /= peak);
+            }
+            chroma
+        })
+        .collect())
+}
+
+/// Per-frame half-wave rectified spectral flux, `sqrt(sum_k max(0, P[t][k] - P[t-1][k])^2)`,
+/// from a power spectrogram. Frame 0 has no predecessor and yields 0.
+pub fn spectral_flux(power_spec: &[Vec<f32>]) -> Vec<f32> {
+    let mut flux = Vec::with_capacity(power_spec.len());
+    if !power_spec.is_empty() {
+        flux.push(0.0);
+    }
+    flux.extend(power_spec.windows(2).map(|pair| {
+        pair[1].iter()
+            .zip(&pair[0])
+            .map(|(current, previous)| (current - previous).max(0.0).powi(2))
+            .sum::<f32>()
+            .sqrt()
+    }));
+    flux
+}
+
+/// Per-frame frequency below which `roll_percent` of the frame's spectral energy lies.
+pub fn spectral_rolloff(power_spec: &[Vec<f32>], fft_freqs: &[f32], roll_percent: f32) -> Result<Vec<f32>, MelError> {
+    if !(roll_percent > 0.0 && roll_percent <= 1.0) {
+        return Err(MelError::InvalidConfig(format!("roll_percent must be in (0, 1], got {}", roll_percent)));
+    }
+
+    Ok(power_spec.iter()
+        .map(|frame| {
+            let threshold = roll_percent * frame.iter().sum::<f32>();
+            let mut cumulative = 0.0;
+            for (power, freq) in frame.iter().zip(fft_freqs) {
+                cumulative += power;
+                if cumulative >= threshold {
+                    return *freq;
+                }
+            }
+            fft_freqs.last().copied().unwrap_or(0.0)
+        })
+        .collect())
+}
+
+/// Berouti-style power spectral subtraction in place: each bin becomes
+/// `P - over_subtraction * N`, but never less than `floor * N`. Bins beyond `noise_estimate`
+/// are left unchanged.
+pub fn spectral_subtraction(power_spec: &mut [Vec<f32>], noise_estimate: &[f32], over_subtraction: f32, floor: f32) {
+    for frame in power_spec.iter_mut() {
+        for (power, &noise) in frame.iter_mut().zip(noise_estimate) {
+            *power = (*power - over_subtraction * noise).max(floor * noise);
+        }
+    }
+}
+
+/// Wiener filtering in place: each bin is scaled by `S / (S + N)` with `S = max(P - N, 0)`.
+/// The gain never drops below the Wiener gain of `snr_floor_db`, `ξ / (1 + ξ)`. Bins beyond
+/// `noise_estimate` are left unchanged.
+pub fn wiener_filter(power_spec: &mut [Vec<f32>], noise_estimate: &[f32], snr_floor_db: f32) {
+    let snr_floor = 10f32.powf(snr_floor_db / 10.0);
+    let min_gain = snr_floor / (1.0 + snr_floor);
+    for frame in power_spec.iter_mut() {
+        for (power, &noise) in frame.iter_mut().zip(noise_estimate) {
+            let signal = (*power - noise).max(0.0);
+            let total = signal + noise;
+            let gain = if total > 0.0 { signal / total } else { 1.0 };
+            *power *= gain.max(min_gain);
+        }
+    }
+}
+
+/// Mean power per bin over the first `n_noise_frames` frames, assumed to hold only noise.
+pub fn estimate_noise_power(power_spec: &[Vec<f32>], n_noise_frames: usize) -> Vec<f32> {
+    let noise_frames = &power_spec[..n_noise_frames.min(power_spec.len())];
+    let mut noise = vec![0.0f32; power_spec.first().map_or(0, Vec::len)];
+    for frame in noise_frames {
+        for (total, power) in noise.iter_mut().zip(frame) {
+            *total += power;
+        }
+    }
+    if !noise_frames.is_empty() {
+        for total in noise.iter_mut() {
+            *total /= noise_frames.len() as f32;
+        }
+    }
+    noise
+}
+
+/// Signal-to-noise ratio in dB of `noisy` against the `clean` reference,
+/// `10 log10(Σ clean² / Σ (noisy - clean)²)`, over the samples both hold.
+pub fn compute_snr_db(clean: &[f32], noisy: &[f32]) -> f32 {
+    let (signal, noise) = clean.iter()
+        .zip(noisy)
+        .fold((0.0f64, 0.0f64), |(signal, noise), (&c, &n)| {
+            (signal + (c as f64).powi(2), noise + (n as f64 - c as f64).powi(2))
+        });
+    (10.0 * (signal / noise).log10()) as f32
+}
+
+/// Root mean square over frames of the Euclidean distance between corresponding frames of `a`
+/// and `b`. NaN when the two differ in frame or mel bin count, so a truncated spectrogram never
+/// passes a distance threshold.
+pub fn compute_mel_distance(a: &MelSpectrogram, b: &MelSpectrogram) -> f32 {
+    if (a.n_frames(), a.n_mels()) != (b.n_frames(), b.n_mels()) {
+        return f32::NAN;
+    }
+    let n_frames = a.n_frames();
+    if n_frames == 0 {
+        return 0.0;
+    }
+    let total: f64 = (0..n_frames)
+        .map(|t| {
+            a.get_frame(t).iter()
+                .zip(b.get_frame(t))
+                .map(|(&x, &y)| (x as f64 - y as f64).powi(2))
+                .sum::<f64>()
+        })
+        .sum();
+    (total / n_frames as f64).sqrt() as f32
+}
+
+/// Mel cepstral distortion in dB between two MFCC sequences, averaged over the frame pairs of
+/// their dynamic-time-warping alignment. Each pair contributes
+/// `(10 / ln 10) * sqrt(2 * Σ_k (c1[k] - c2[k])²)` over every coefficient given, so drop `c0`
+/// beforehand for the usual energy-independent figure.
+pub fn mel_cepstral_distortion(ref_mfcc: &[Vec<f32>], synth_mfcc: &[Vec<f32>]) -> Result<f32, MelError> {
+    let (Some(first), false) = (ref_mfcc.first(), synth_mfcc.is_empty()) else {
+        return Err(MelError::InvalidAudio("Both MFCC sequences need at least one frame".to_string()));
+    };
+    let n_mfcc = first.len();
+    if let Some(frame) = ref_mfcc.iter().chain(synth_mfcc).find(|frame| frame.len() != n_mfcc) {
+        return Err(MelError::InvalidConfig(format!(
+            "Every MFCC frame must hold {} coefficients, found one with {}",
+            n_mfcc,
+            frame.len()
+        )));
+    }
+
+    let scale = 10.0 / std::f64::consts::LN_10 * 2f64.sqrt();
+    let frame_mcd = |a: &[f32], b: &[f32]| {
+        scale * a.iter().zip(b).map(|(&x, &y)| (x as f64 - y as f64).powi(2)).sum::<f64>().sqrt()
+    };
+
+    // Cumulative (distortion, path length) over rows of the alignment grid.
+    let mut prev: Vec<(f64, usize)> = Vec::with_capacity(synth_mfcc.len());
+    for (i, r) in ref_mfcc.iter().enumerate() {
+        let mut row: Vec<(f64, usize)> = Vec::with_capacity(synth_mfcc.len());
+        for (j, s) in synth_mfcc.iter().enumerate() {
+            let best = [
+                (i > 0 && j > 0).then(|| prev[j - 1]),
+                (i > 0).then(|| prev[j]),
+                (j > 0).then(|| row[j - 1]),
+            ]
+            .into_iter()
+            .flatten()
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .unwrap_or((0.0, 0));
+            row.push((best.0 + frame_mcd(r, s), best.1 + 1));
+        }
+        prev = row;
+    }
+
+    let (total, steps) = prev[synth_mfcc.len() - 1];
+    Ok((total / steps as f64) as f32)
+}
+
+/// Warp factor in `warp_range` under which the MFCCs of `samples` are most likely according to
+/// a diagonal-covariance GMM, found by `n_steps` rounds of golden-section search. Each
+/// candidate warps `context`'s mel filter bank with [`dsp::vtln_warp_mel_filterbank`] and
+/// re-extracts MFCCs (with the context's deltas), so the GMM dimension must be a multiple of
+/// `1 + delta_order`. The likelihood is assumed unimodal over `warp_range`.
+#[allow(clippy::too_many_arguments)]
+pub fn estimate_vtln_warp(
+    context: &MelExtractorContext,
+    samples: &[f32],
+    sample_rate: u32,
+    gmm_means: &[Vec<f32>],
+    gmm_vars: &[Vec<f32>],
+    gmm_weights: &[f32],
+    warp_range: (f32, f32),
+    n_steps: usize,
+) -> Result<f32, MelError> {
+    let config = context.config();
+    if config.filter_bank_type != FilterBankType::Mel {
+        return Err(MelError::InvalidConfig("VTLN warp estimation needs a mel filter bank".to_string()));
+    }
+    let (low, high) = warp_range;
+    if !(low > 0.0 && low <= high && high.is_finite()) {
+        return Err(MelError::InvalidConfig(format!("Invalid VTLN warp range ({}, {})", low, high)));
+    }
+    let dim = gmm_means.first().map_or(0, Vec::len);
+    let n_components = gmm_weights.len();
+    if dim == 0 || gmm_means.len() != n_components || gmm_vars.len() != n_components {
+        return Err(MelError::InvalidConfig(format!(
+            "GMM has {} weights, {} means and {} variances",
+            n_components,
+            gmm_means.len(),
+            gmm_vars.len()
+        )));
+    }
+    if gmm_means.iter().chain(gmm_vars).any(|component| component.len() != dim) {
+        return Err(MelError::InvalidConfig(format!("Every GMM mean and variance must have {} dimensions", dim)));
+    }
+    if gmm_vars.iter().flatten().any(|&var| var <= 0.0) || gmm_weights.iter().any(|&weight| weight <= 0.0) {
+        return Err(MelError::InvalidConfig("GMM variances and weights must be positive".to_string()));
+    }
+    let blocks = 1 + config.delta_order.min(2) as usize;
+    if !dim.is_multiple_of(blocks) {
+        return Err(MelError::InvalidConfig(format!(
+            "GMM dimension {} is not a multiple of {} coefficient blocks",
+            dim, blocks
+        )));
+    }
+
+    let log_likelihood = |warp_alpha: f32| -> Result<f64, MelError> {
+        let mut warped = context.clone();
+        warped.mel_filters = dsp::vtln_warp_mel_filterbank(&context.mel_filters, warp_alpha, config)?;
+        let features = warped.compute_log_mel(samples.to_vec(), sample_rate, &|_| {})?;
+        let mfcc = append_deltas(mel_to_mfcc(&features.log_mel, dim / blocks)?, config.delta_order)?;
+        Ok(gmm_average_log_likelihood(&mfcc, gmm_means, gmm_vars, gmm_weights))
+    };
+
+    let inv_phi = (5f32.sqrt() - 1.0) / 2.0;
+    let (mut a, mut b) = (low, high);
+    let mut c = b - inv_phi * (b - a);
+    let mut d = a + inv_phi * (b - a);
+    let (mut score_c, mut score_d) = if n_steps > 0 { (log_likelihood(c)?, log_likelihood(d)?) } else { (0.0, 0.0) };
+    for _ in 1..n_steps {
+        if score_c >= score_d {
+            b = d;
+            (d, score_d) = (c, score_c);
+            c = b - inv_phi * (b - a);
+            score_c = log_likelihood(c)?;
+        } else {
+            a = c;
+            (c, score_c) = (d, score_d);
+            d = a + inv_phi * (b - a);
+            score_d = log_likelihood(d)?;
+        }
+    }
+    Ok(match n_steps {
+        0 => (a + b) / 2.0,
+        _ if score_c >= score_d => c,
+        _ => d,
+    })
+}
+
+/// Mean per-frame log-likelihood of `features` under a diagonal-covariance GMM.
+fn gmm_average_log_likelihood(features: &[Vec<f32>], means: &[Vec<f32>], vars: &[Vec<f32>], weights: &[f32]) -> f64 {
+    if features.is_empty() {
+        return f64::NEG_INFINITY;
+    }
+    let weight_sum: f64 = weights.iter().map(|&weight| weight as f64).sum();
+    // log(w_k) - ½ Σ log(2π σ²) for each component.
+    let constants: Vec<f64> = weights.iter()
+        .zip(vars)
+        .map(|(&weight, var)| {
+            (weight as f64 / weight_sum).ln()
+                - 0.5 * var.iter().map(|&v| (2.0 * std::f64::consts::PI * v as f64).ln()).sum::<f64>()
+        })
+        .collect();
+    let total: f64 = features.iter()
+        .map(|frame| {
+            let component_scores: Vec<f64> = means.iter()
+                .zip(vars)
+                .zip(&constants)
+                .map(|((mean, var), &constant)| {
+                    constant - 0.5 * frame.iter()
+                        .zip(mean)
+                        .zip(var)
+                        .map(|((&x, &m), &v)| (x as f64 - m as f64).powi(2) / v as f64)
+                        .sum::<f64>()
+                })
+                .collect();
+            let max = component_scores.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            max + component_scores.iter().map(|&score| (score - max).exp()).sum::<f64>().ln()
+        })
+        .sum();
+    total / features.len() as f64
+}
+
+const CQT_HOP_LENGTH: usize = 512;
+const CQT_KERNEL_THRESHOLD: f32 = 0.0054;
+
+/// Constant-Q power spectrogram, `[n_frames][n_bins]`, with bin `k` centred on
+/// `fmin * 2^(k / bins_per_octave)`. Uses the Brown-Puckette spectral kernel on a centred STFT
+/// with a hop of 512 samples.
+pub fn constant_q_transform(
+    samples: &[f32],
+    sample_rate: u32,
+    n_bins: usize,
+    bins_per_octave: usize,
+    fmin: f32,
+) -> Result<Vec<Vec<f32>>, MelError> {
+    if n_bins == 0 || bins_per_octave == 0 {
+        return Err(MelError::InvalidConfig("n_bins and bins_per_octave must be greater than 0".to_string()));
+    }
+    let nyquist = sample_rate as f32 / 2.0;
+    let fmax = fmin * 2f32.powf((n_bins - 1) as f32 / bins_per_octave as f32);
+    if !(fmin > 0.0 && fmax < nyquist) {
+        return Err(MelError::InvalidConfig(format!(
+            "CQT bins must lie in (0, {}) Hz, got {} to {} Hz",
+            nyquist, fmin, fmax
+        )));
+    }
+    if samples.is_empty() {
+        return Err(MelError::InvalidAudio("No samples to transform".to_string()));
+    }
+
+    let q = 1.0 / (2f32.powf(1.0 / bins_per_octave as f32) - 1.0);
+    let longest_kernel = (q * sample_rate as f32 / fmin).ceil() as usize;
+    let fft_len = longest_kernel.next_power_of_two();
+    let kernel = cq_kernel(sample_rate, n_bins, bins_per_octave, fmin, q, fft_len);
+
+    let pad = fft_len / 2;
+    let mut padded = vec![0.0; pad];
+    padded.extend_from_slice(samples);
+    padded.resize(padded.len() + pad, 0.0);
+    let n_frames = 1 + samples.len() / CQT_HOP_LENGTH;
+
+    let r2c = RealFftPlanner::<f32>::new().plan_fft_forward(fft_len);
+    let mut input = r2c.make_input_vec();
+    let mut spectrum = r2c.make_output_vec();
+    let mut cqt = Vec::with_capacity(n_frames);
+    for frame in 0..n_frames {
+        let start = frame * CQT_HOP_LENGTH;
+        input.copy_from_slice(&padded[start..start + fft_len]);
+        r2c.process(&mut input, &mut spectrum)?;
+        cqt.push(
+            kernel.iter()
+                .map(|bin| {
+                    let value: Complex<f32> = bin.iter().map(|&(j, k)| spectrum[j] * k.conj()).sum();
+                    (value / fft_len as f32).norm_sqr()
+                })
+                .collect(),
+        );
+    }
+
+    Ok(cqt)
+}
+
+/// Sparse spectral kernels of the CQT filters, one `(rfft bin, weight)` list per CQT bin.
+fn cq_kernel(
+    sample_rate: u32,
+    n_bins: usize,
+    bins_per_octave: usize,
+    fmin: f32,
+    q: f32,
+    fft_len: usize,
+) -> Vec<Vec<(usize, Complex<f32>)>> {
+    let fft = rustfft::FftPlanner::<f32>::new().plan_fft_forward(fft_len);
+    (0..n_bins)
+        .map(|k| {
+            let freq = fmin * 2f32.powf(k as f32 / bins_per_octave as f32);
+            let len = ((q * sample_rate as f32 / freq).ceil() as usize).min(fft_len);
+            let window = make_window(len, WindowType::Hann);
+            let offset = (fft_len - len) / 2;
+
+            let mut buffer = vec![Complex::new(0.0, 0.0); fft_len];
+            for (n, w) in window.iter().enumerate() {
+                let phase = 2.0 * std::f32::consts::PI * q * n as f32 / len as f32;
+                buffer[offset + n] = Complex::from_polar(w / len as f32, phase);
+            }
+            fft.process(&mut buffer);
+
+            buffer[..=fft_len / 2]
+                .iter()
+                .enumerate()
+                .filter(|(_, value)| value.norm() > CQT_KERNEL_THRESHOLD)
+                .map(|(j, value)| (j, *value))
+                .collect()
+        })
+        .collect()
+}
+
+/// Power spectrograms of `samples` at each resolution in `configs`, as separate
+/// `[n_frames][n_fft / 2 + 1]` matrices in the same order. Frames are not centre-padded.
+pub fn multi_resolution_stft(samples: &[f32], configs: &[StftConfig]) -> Result<Vec<Vec<Vec<f32>>>, MelError> {
+    // `frame_signal_views` panics on a zero frame or hop length.
+    if let Some(stft) = configs.iter().find(|stft| stft.n_fft == 0 || stft.hop_length == 0) {
+        return Err(MelError::InvalidConfig(format!(
+            "STFT n_fft ({}) and hop_length ({}) must be positive",
+            stft.n_fft, stft.hop_length
+        )));
+    }
+
+    let mut planner = RealFftPlanner::<f32>::new();
+    configs.iter()
+        .map(|stft| {
+            let framed = frame_signal_views(samples, stft.n_fft, stft.hop_length).map(<[f32]>::to_vec).collect();
+            let windowed = apply_window(framed, &make_window(stft.n_fft, stft.window_type))?;
+            let r2c = planner.plan_fft_forward(stft.n_fft);
+            power_spectrogram(apply_rfft(windowed, r2c.as_ref())?)
+        })
+        .collect()
+}
+
+/// The filter bank selected by `config.filter_bank_type`.
+fn filter_bank(config: &MelConfig) -> Result<MelFilterBank, MelError> {
+    match config.filter_bank_type {
+        FilterBankType::Mel => mel_filter_bank(config),
+        FilterBankType::Bark => bark_filter_bank(config.n_fft, config.n_mels, config.sample_rate),
+        FilterBankType::Erb => {
+            erb_filter_bank(config.n_fft, config.n_mels, config.min_freq, config.max_freq, config.sample_rate)
+        }
+    }
+}
+
+#[cfg(feature = "simd")]
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    use wide::f32x8;
+
+    let a_chunks = a.chunks_exact(8);
+    let b_chunks = b.chunks_exact(8);
+    let tail: f32 = a_chunks.remainder().iter()
+        .zip(b_chunks.remainder())
+        .map(|(x, y)| x * y)
+        .sum();
+
+    let mut acc = f32x8::ZERO;
+    for (x, y) in a_chunks.zip(b_chunks) {
+        let x = f32x8::from(<[f32; 8]>::try_from(x).unwrap());
+        let y = f32x8::from(<[f32; 8]>::try_from(y).unwrap());
+        acc = x.mul_add(y, acc);
+    }
+
+    acc.reduce_add() + tail
+}
+
+#[cfg(not(feature = "simd"))]
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    let mut sum = 0.0;
+    for (x, y) in a.iter().zip(b) {
+        sum += x * y;
+    }
+    sum
+}
+
+fn transpose(matrix: Vec<Vec<f32>>) -> Result<Vec<Vec<f32>>, MelError> {
+    let n_rows = matrix.len();
+    let n_cols = matrix.first().map_or(0, Vec::len);
+    if let Some(row) = matrix.iter().find(|row| row.len() != n_cols) {
+        return Err(MelError::InvalidConfig(format!(
+            "Cannot transpose jagged matrix: expected rows of length {}, found {}",
+            n_cols, row.len()
+        )));
+    }
+
+    let mut transposed = vec![vec![0.0f32; n_rows]; n_cols];
+    for (i, row) in matrix.into_iter().enumerate() {
+        for (j, val) in row.into_iter().enumerate() {
+            transposed[j][i] = val;
+        }
+    }
+
+    Ok(transposed)
+}
+
+/// Converts a `[n_frames][n_mels]` log-mel spectrogram to `[n_frames][n_mfcc]` cepstral
+/// coefficients using an orthonormal DCT-II along the mel axis (scipy's `norm='ortho'`, as
+/// used by librosa).
+pub fn mel_to_mfcc(mel_log: &[Vec<f32>], n_mfcc: usize) -> Result<Vec<Vec<f32>>, MelError> {
+    let n_mels = mel_log.first().map_or(0, Vec::len);
+    if n_mfcc == 0 || n_mfcc > n_mels {
+        return Err(MelError::InvalidConfig(format!(
+            "n_mfcc ({}) must be between 1 and the number of mel bins ({})",
+            n_mfcc, n_mels
+        )));
+    }
+    if mel_log.iter().any(|frame| frame.len() != n_mels) {
+        return Err(MelError::InvalidConfig("All mel frames must have the same length".to_string()));
+    }
+
+    let scale = (2.0 / n_mels as f32).sqrt();
+    let dct_basis: Vec<Vec<f32>> = (0..n_mfcc)
+        .map(|n| {
+            let norm = if n == 0 { scale * std::f32::consts::FRAC_1_SQRT_2 } else { scale };
+            (0..n_mels)
+                .map(|k| norm * (std::f32::consts::PI * n as f32 * (k as f32 + 0.5) / n_mels as f32).cos())
+                .collect()
+        })
+        .collect();
+
+    Ok(mel_log.iter()
+        .map(|frame| {
+            dct_basis.iter()
+                .map(|basis| basis.iter().zip(frame).map(|(b, m)| b * m).sum())
+                .collect()
+        })
+        .collect()
+    )
+}
+
+/// Linear prediction coefficients `a_1..a_order` of `frame` by the autocorrelation method, so
+/// that `x[n] ≈ Σ a_k x[n - k]`. A silent frame yields all zeros.
+pub fn compute_lpc(frame: &[f32], order: usize) -> Result<Vec<f32>, MelError> {
+    levinson_durbin(frame, order).map(|(lpc, _)| lpc)
+}
+
+/// LPC cepstrum `c_0..c_{n_ceps - 1}` from predictor coefficients, with `c_0 = log_gain`.
+pub fn lpc_to_lpcc(lpc: &[f32], log_gain: f32, n_ceps: usize) -> Vec<f32> {
+    let order = lpc.len();
+    let mut cepstrum = Vec::with_capacity(n_ceps);
+    for n in 0..n_ceps {
+        if n == 0 {
+            cepstrum.push(log_gain);
+            continue;
+        }
+        let mut c = if n <= order { lpc[n - 1] } else { 0.0 };
+        for k in n.saturating_sub(order).max(1)..n {
+            c += (k as f32 / n as f32) * cepstrum[k] * lpc[n - k - 1];
+        }
+        cepstrum.push(c);
+    }
+    cepstrum
+}
+
+/// `n_ceps` LPC cepstral coefficients per frame from an order-`order` LPC fit, with the log of
+/// the prediction gain as `c_0`.
+pub fn extract_lpcc(frames: &[Vec<f32>], order: usize, n_ceps: usize) -> Result<Vec<Vec<f32>>, MelError> {
+    frames.iter()
+        .map(|frame| {
+            let (lpc, error) = levinson_durbin(frame, order)?;
+            let log_gain = 0.5 * error.max(1e-10).ln();
+            Ok(lpc_to_lpcc(&lpc, log_gain, n_ceps))
+        })
+        .collect()
+}
+
+/// Levinson-Durbin recursion on the frame's autocorrelation, returning the predictor
+/// coefficients and the final prediction error energy.
+fn levinson_durbin(frame: &[f32], order: usize) -> Result<(Vec<f32>, f32), MelError> {
+    if order == 0 || order >= frame.len() {
+        return Err(MelError::InvalidConfig(format!(
+            "LPC order ({}) must be between 1 and the frame length minus one ({})",
+            order,
+            frame.len().saturating_sub(1)
+        )));
+    }
+
+    let autocorr: Vec<f64> = (0..=order)
+        .map(|lag| frame[lag..].iter().zip(frame).map(|(&x, &y)| x as f64 * y as f64).sum())
+        .collect();
+    let mut lpc = vec![0.0f64; order];
+    let mut error = autocorr[0];
+    if error <= 0.0 {
+        return Ok((vec![0.0; order], 0.0));
+    }
+
+    for i in 0..order {
+        let mut acc = autocorr[i + 1];
+        for j in 0..i {
+            acc -= lpc[j] * autocorr[i - j];
+        }
+        let reflection = acc / error;
+        let previous = lpc.clone();
+        lpc[i] = reflection;
+        for j in 0..i {
+            lpc[j] = previous[j] - reflection * previous[i - j - 1];
+        }
+        error *= 1.0 - reflection * reflection;
+        if error <= 0.0 {
+            break;
+        }
+    }
+
+    Ok((lpc.into_iter().map(|a| a as f32).collect(), error.max(0.0) as f32))
+}
+
+/// Converts power values to decibels in place following librosa's `power_to_db`:
+/// `10 * log10(max(amin, S)) - 10 * log10(max(amin, ref_power))`, then clips everything more
+/// than `top_db` below the peak.
+pub fn power_to_db(spectrogram: &mut [Vec<f32>], ref_power: f32, amin: f32, top_db: Option<f32>) {
+    scale_to_db(spectrogram, 10.0, ref_power, amin, top_db);
+}
+
+/// Like [`power_to_db`], but for magnitudes: `20 * log10(max(amin, S) / max(amin, ref))`.
+pub fn amplitude_to_db(spectrogram: &mut [Vec<f32>], ref_amplitude: f32, amin: f32, top_db: Option<f32>) {
+    scale_to_db(spectrogram, 20.0, ref_amplitude, amin, top_db);
+}
+
+fn scale_to_db(spectrogram: &mut [Vec<f32>], multiplier: f32, reference: f32, amin: f32, top_db: Option<f32>) {
+    let ref_db = multiplier * reference.abs().max(amin).log10();
+    let mut max_db = f32::NEG_INFINITY;
+
+    for row in spectrogram.iter_mut() {
+        for val in row.iter_mut() {
+            *val = multiplier * val.max(amin).log10() - ref_db;
+            max_db = max_db.max(*val);
+        }
+    }
+
+    if let Some(top_db) = top_db {
+        let floor_db = max_db - top_db;
+        for row in spectrogram.iter_mut() {
+            for val in row.iter_mut() {
+                *val = val.max(floor_db);
+            }
+        }
+    }
+}
+
+fn apply_output_scale(mut mel_spectrogram: Vec<Vec<f32>>, config: &MelConfig) -> Result<Vec<Vec<f32>>, MelError> {
+    if let NormalizationMode::Pcen(pcen) = &config.normalization {
+        apply_pcen(&mut mel_spectrogram, pcen);
+        return Ok(mel_spectrogram);
+    }
+    match config.output_scale {
+        SpectrogramScale::Raw => Ok(mel_spectrogram),
+        SpectrogramScale::Log10 => apply_log(mel_spectrogram, config.log_base, config.log_floor),
+        SpectrogramScale::PowerDb => {
+            power_to_db(&mut mel_spectrogram, 1.0, config.log_floor, config.top_db);
+            Ok(mel_spectrogram)
+        }
+        SpectrogramScale::AmplitudeDb => {
+            amplitude_to_db(&mut mel_spectrogram, 1.0, config.log_floor, config.top_db);
+            Ok(mel_spectrogram)
+        }
+    }
+}
+
+/// Concatenates frames `t - left ..= t + right` into one vector per frame `t`, replicating the
+/// first and last frames at the edges. Each output frame holds `n_features * (left + 1 + right)`
+/// values, with frame `t` itself at offset `n_features * left`.
+pub fn stack_context(features: &[Vec<f32>], left: usize, right: usize) -> Vec<Vec<f32>> {
+    let last = features.len().saturating_sub(1);
+    (0..features.len())
+        .map(|t| {
+            (t as isize - left as isize..=(t + right) as isize)
+                .flat_map(|i| features[(i.max(0) as usize).min(last)].iter().copied())
+                .collect()
+        })
+        .collect()
+}
+
+/// Joins per-frame feature streams, such as log-mels, MFCCs and deltas, into one wider vector
+/// per frame, in stream order. Every stream must have the same number of frames.
+pub fn concatenate_features(streams: &[&[Vec<f32>]]) -> Result<Vec<Vec<f32>>, MelError> {
+    let n_frames = streams.first().map_or(0, |stream| stream.len());
+    if let Some(stream) = streams.iter().find(|stream| stream.len() != n_frames) {
+        return Err(MelError::InvalidConfig(format!(
+            "All feature streams must have {} frames, found one with {}",
+            n_frames,
+            stream.len()
+        )));
+    }
+    Ok((0..n_frames)
+        .map(|t| streams.iter().flat_map(|stream| stream[t].iter().copied()).collect())
+        .collect())
+}
+
+/// Undoes [`concatenate_features`]: cuts every frame at the feature offsets in `split_at`,
+/// returning `split_at.len() + 1` streams. Offsets past the end of a frame are clamped to it.
+pub fn split_features(features: &[Vec<f32>], split_at: &[usize]) -> Vec<Vec<Vec<f32>>> {
+    let mut streams = vec![Vec::with_capacity(features.len()); split_at.len() + 1];
+    for frame in features {
+        let mut start = 0;
+        for (stream, end) in streams.iter_mut().zip(split_at.iter().copied().chain([frame.len()])) {
+            let end = end.clamp(start, frame.len());
+            stream.push(frame[start..end].to_vec());
+            start = end;
+        }
+    }
+    streams
+}
+
+/// Replaces each value with the median of its bin over the `kernel_size` frames centred on it,
+/// replicating the first and last frames at the edges. An even `kernel_size` is rounded up to
+/// the next odd one; a sorted window is updated incrementally as it slides along each bin.
+pub fn median_filter_time(spectrogram: &mut [Vec<f32>], kernel_size: usize) {
+    let half = kernel_size / 2;
+    let n_frames = spectrogram.len();
+    if half == 0 || n_frames == 0 {
+        return;
+    }
+    let n_bins = spectrogram.iter().map(Vec::len).min().unwrap_or(0);
+    let last = (n_frames - 1) as isize;
+    let mut window = Vec::with_capacity(2 * half + 1);
+
+    for bin in 0..n_bins {
+        let column: Vec<f32> = spectrogram.iter().map(|frame| frame[bin]).collect();
+        let at = |t: isize| column[t.clamp(0, last) as usize];
+        window.clear();
+        window.extend((-(half as isize)..=half as isize).map(at));
+        window.sort_by(f32::total_cmp);
+
+        for (t, frame) in spectrogram.iter_mut().enumerate() {
+            frame[bin] = window[half];
+            let outgoing = at(t as isize - half as isize);
+            let incoming = at((t + half + 1) as isize);
+            window.remove(window.partition_point(|x| x.total_cmp(&outgoing).is_lt()));
+            window.insert(window.partition_point(|x| x.total_cmp(&incoming).is_lt()), incoming);
+        }
+    }
+}
+
+/// Subtracts the utterance mean of each column of `[n_frames][n_features]` features.
+pub fn cepstral_mean_subtraction(features: Vec<Vec<f32>>) -> Vec<Vec<f32>> {
+    let n_features = features.first().map_or(0, Vec::len);
+    let mut mean = vec![0.0f64; n_features];
+    for frame in &features {
+        for (sum, &x) in mean.iter_mut().zip(frame) {
+            *sum += x as f64;
+        }
+    }
+    let mean: Vec<f32> = mean.iter().map(|sum| (sum / features.len().max(1) as f64) as f32).collect();
+    subtract_mean(features, &mean)
+}
+
+/// Subtracts a precomputed, e.g. corpus-wide, per-column mean from `[n_frames][n_features]`
+/// features.
+pub fn cepstral_mean_subtraction_with_stats(features: Vec<Vec<f32>>, global_mean: &[f32]) -> Result<Vec<Vec<f32>>, MelError> {
+    if let Some(frame) = features.iter().find(|frame| frame.len() != global_mean.len()) {
+        return Err(MelError::InvalidConfig(format!(
+            "Mean has {} values but a frame has {} features",
+            global_mean.len(),
+            frame.len()
+        )));
+    }
+    Ok(subtract_mean(features, global_mean))
+}
+
+fn subtract_mean(mut features: Vec<Vec<f32>>, mean: &[f32]) -> Vec<Vec<f32>> {
+    for frame in features.iter_mut() {
+        for (x, m) in frame.iter_mut().zip(mean) {
+            *x -= m;
+        }
+    }
+    features
+}
+
+/// Normalises each column of `[n_frames][n_bins]` features to zero mean and unit variance, over
+/// the whole utterance or a centred sliding window. Bins with zero variance are only centred.
+pub fn apply_cmvn(features: &mut [Vec<f32>], mode: CmvnMode) -> Result<(), MelError> {
+    let n_frames = features.len();
+    let n_bins = features.first().map_or(0, Vec::len);
+    if features.iter().any(|frame| frame.len() != n_bins) {
+        return Err(MelError::InvalidConfig("All frames must have the same number of bins".to_string()));
+    }
+
+    let half_width = match mode {
+        CmvnMode::None => return Ok(()),
+        CmvnMode::Global => n_frames,
+        CmvnMode::SlidingWindow { width: 0 } => {
+            return Err(MelError::InvalidConfig("CMVN window width must be greater than 0".to_string()));
+        }
+        CmvnMode::SlidingWindow { width } => width / 2,
+    };
+
+    for bin in 0..n_bins {
+        // Prefix sums in f64 so long utterances do not lose precision.
+        let mut sums = vec![(0.0f64, 0.0f64); n_frames + 1];
+        for (t, frame) in features.iter().enumerate() {
+            let x = frame[bin] as f64;
+            sums[t + 1] = (sums[t].0 + x, sums[t].1 + x * x);
+        }
+        for (t, frame) in features.iter_mut().enumerate() {
+            let start = t.saturating_sub(half_width);
+            let end = (t + half_width + 1).min(n_frames);
+            let count = (end - start) as f64;
+            let mean = (sums[end].0 - sums[start].0) / count;
+            let variance = ((sums[end].1 - sums[start].1) / count - mean * mean).max(0.0);
+            let std = variance.sqrt();
+            let centred = frame[bin] as f64 - mean;
+            frame[bin] = if std > 1e-10 { centred / std } else { centred } as f32;
+        }
+    }
+    Ok(())
+}
+
+/// Divides every frame by its L2 norm; frames with zero norm become all zeros.
+pub fn l2_normalize_frames(features: &mut [Vec<f32>]) {
+    for frame in features.iter_mut() {
+        let scale = inverse_l2_norm(frame.iter());
+        frame.iter_mut().for_each(|x| *x *= scale);
+    }
+}
+
+/// Divides every value by the L2 norm of the whole flattened matrix.
+pub fn l2_normalize_global(features: &mut [Vec<f32>]) {
+    let scale = inverse_l2_norm(features.iter().flatten());
+    features.iter_mut().flatten().for_each(|x| *x *= scale);
+}
+
+/// `1 / ||values||`, or 0 for an all-zero input.
+fn inverse_l2_norm<'a>(values: impl Iterator<Item = &'a f32>) -> f32 {
+    let norm = values.map(|&x| (x as f64).powi(2)).sum::<f64>().sqrt();
+    if norm > 0.0 { (1.0 / norm) as f32 } else { 0.0 }
+}
+
+/// Per-bin running sums over many spectrograms, for corpus-wide mean and variance
+/// normalisation with [`apply_global_mvn`].
+#[derive(Debug, Clone, Default)]
+pub struct CorpusStatsAccumulator {
+    n_frames: u64,
+    sum: Vec<f64>,
+    sum_sq: Vec<f64>,
+}
+
+impl CorpusStatsAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds every frame of `spectrogram`. All spectrograms must have the same number of bins.
+    pub fn update(&mut self, spectrogram: &MelSpectrogram) -> Result<(), MelError> {
+        if self.n_frames == 0 {
+            self.sum = vec![0.0; spectrogram.n_mels()];
+            self.sum_sq = vec![0.0; spectrogram.n_mels()];
+        } else if spectrogram.n_mels() != self.sum.len() {
+            return Err(MelError::InvalidConfig(format!(
+                "Spectrogram has {} bins but the accumulated statistics have {}",
+                spectrogram.n_mels(),
+                self.sum.len()
+            )));
+        }
+        for frame in spectrogram.iter_frames() {
+            for ((sum, sum_sq), &x) in self.sum.iter_mut().zip(self.sum_sq.iter_mut()).zip(frame) {
+                *sum += x as f64;
+                *sum_sq += x as f64 * x as f64;
+            }
+        }
+        self.n_frames += spectrogram.n_frames() as u64;
+        Ok(())
+    }
+
+    /// Frames accumulated so far.
+    pub fn n_frames(&self) -> u64 {
+        self.n_frames
+    }
+
+    /// Per-bin mean and population variance of every frame seen, empty before the first one.
+    pub fn finalize(&self) -> (Vec<f32>, Vec<f32>) {
+        if self.n_frames == 0 {
+            return (Vec::new(), Vec::new());
+        }
+        let n = self.n_frames as f64;
+        self.sum.iter()
+            .zip(&self.sum_sq)
+            .map(|(&sum, &sum_sq)| {
+                let mean = sum / n;
+                (mean as f32, (sum_sq / n - mean * mean).max(0.0) as f32)
+            })
+            .unzip()
+    }
+}
+
+/// Normalises every bin of `spec` to `(x - mean) / sqrt(variance)` with corpus statistics such
+/// as those of [`CorpusStatsAccumulator::finalize`]. Bins with zero variance are only centred.
+pub fn apply_global_mvn(spec: &mut MelSpectrogram, mean: &[f32], variance: &[f32]) -> Result<(), MelError> {
+    if mean.len() != spec.n_mels || variance.len() != spec.n_mels {
+        return Err(MelError::InvalidConfig(format!(
+            "Statistics have {} means and {} variances for {} bins",
+            mean.len(),
+            variance.len(),
+            spec.n_mels
+        )));
+    }
+    let scale: Vec<f32> = variance.iter()
+        .map(|&var| if var.sqrt() > 1e-10 { 1.0 / var.sqrt() } else { 1.0 })
+        .collect();
+    for frame in spec.data.chunks_exact_mut(spec.n_mels.max(1)) {
+        for ((x, &m), &s) in frame.iter_mut().zip(mean).zip(&scale) {
+            *x = (*x - m) * s;
+        }
+    }
+    Ok(())
+}
+
+const DELTA_WIDTH: usize = 2;
+
+fn reflect_index(i: isize, len: usize) -> usize {
+    if len == 1 {
+        return 0;
+    }
+    let period = 2 * (len as isize - 1);
+    let i = i.rem_euclid(period);
+    if i >= len as isize {
+        (period - i) as usize
+    } else {
+        i as usize
+    }
+}
+
+/// Computes `[n_frames][n_features]` regression deltas over `±width` frames, reflecting the
+/// sequence at its edges.
+pub fn compute_delta(features: &[Vec<f32>], width: usize) -> Result<Vec<Vec<f32>>, MelError> {
+    if width == 0 {
+        return Err(MelError::InvalidConfig("Delta width must be positive".to_string()));
+    }
+    let n_frames = features.len();
+    let n_features = features.first().map_or(0, Vec::len);
+    if features.iter().any(|frame| frame.len() != n_features) {
+        return Err(MelError::InvalidConfig("All feature frames must have the same length".to_string()));
+    }
+
+    let denominator: f32 = 2.0 * (1..=width).map(|n| (n * n) as f32).sum::<f32>();
+    let mut deltas = vec![vec![0.0f32; n_features]; n_frames];
+
+    for (t, delta) in deltas.iter_mut().enumerate() {
+        for n in 1..=width {
+            let next = &features[reflect_index(t as isize + n as isize, n_frames)];
+            let prev = &features[reflect_index(t as isize - n as isize, n_frames)];
+            for (d, (a, b)) in delta.iter_mut().zip(next.iter().zip(prev)) {
+                *d += n as f32 * (a - b);
+            }
+        }
+        for d in delta.iter_mut() {
+            *d /= denominator;
+        }
+    }
+
+    Ok(deltas)
+}
+
+/// Second-order deltas: the deltas of [`compute_delta`]'s output.
+pub fn compute_delta_delta(features: &[Vec<f32>], width: usize) -> Result<Vec<Vec<f32>>, MelError> {
+    compute_delta(&compute_delta(features, width)?, width)
+}
+
+fn append_deltas(mut features: Vec<Vec<f32>>, delta_order: u8) -> Result<Vec<Vec<f32>>, MelError> {
+    if delta_order == 0 || features.is_empty() {
+        return Ok(features);
+    }
+
+    let delta = compute_delta(&features, DELTA_WIDTH)?;
+    let delta_delta = if delta_order >= 2 {
+        Some(compute_delta(&delta, DELTA_WIDTH)?)
+    } else {
+        None
+    };
+
+    for (t, frame) in features.iter_mut().enumerate() {
+        frame.extend_from_slice(&delta[t]);
+        if let Some(delta_delta) = &delta_delta {
+            frame.extend_from_slice(&delta_delta[t]);
+        }
+    }
+
+    Ok(features)
+}
+
+/// Reconstructs a waveform at `config.sample_rate` from `[n_frames][n_mels]` features produced
+/// with `config`: the output scale is undone, the mel filter bank is inverted with its
+/// pseudoinverse, and the phase is estimated with `n_iter` Griffin-Lim iterations. Appended
+/// delta columns are ignored.
+pub fn griffin_lim(mel_spectrogram: &[Vec<f32>], config: &MelConfig, n_iter: usize) -> Result<Vec<f32>, MelError> {
+    config.validate()?;
+    if mel_spectrogram.is_empty() {
+        return Err(MelError::InvalidAudio("Mel spectrogram has no frames".to_string()));
+    }
+    if mel_spectrogram.iter().any(|frame| frame.len() < config.n_mels) {
+        return Err(MelError::InvalidConfig(format!(
+            "Every frame must hold at least n_mels ({}) values",
+            config.n_mels
+        )));
+    }
+
+    if let NormalizationMode::Pcen(_) = config.normalization {
+        return Err(MelError::InvalidConfig("PCEN output cannot be inverted".to_string()));
+    }
+
+    let mel_power = invert_output_scale(mel_spectrogram, config);
+    let inverse_filters = mel_pseudo_inverse(&filter_bank(config)?)?;
+    let magnitudes: Vec<Vec<f32>> = mel_power.iter()
+        .map(|frame| {
+            inverse_filters.iter()
+                .map(|row| {
+                    let value = dot(row, frame).max(0.0);
+                    match config.spectrogram_type {
+                        SpectrogramType::Power => value.sqrt(),
+                        SpectrogramType::Amplitude => value,
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    let window = make_window(config.frame_length, config.window_type);
+    let mut planner = RealFftPlanner::<f32>::new();
+    let r2c = planner.plan_fft_forward(config.frame_length);
+    let c2r = planner.plan_fft_inverse(config.frame_length);
+
+    let mut spectrum: Vec<Vec<Complex<f32>>> = magnitudes.iter()
+        .map(|frame| frame.iter().map(|&m| Complex::new(m, 0.0)).collect())
+        .collect();
+    let mut signal = overlap_add(&spectrum, &window, config.hop_length, c2r.as_ref())?;
+    for _ in 0..n_iter {
+        let frames = (0..magnitudes.len())
+            .map(|i| {
+                let start = i * config.hop_length;
+                signal[start..start + config.frame_length].to_vec()
+            })
+            .collect();
+        let rebuilt = apply_rfft(apply_window(frames, &window)?, r2c.as_ref())?;
+        for ((frame, rebuilt_frame), target) in spectrum.iter_mut().zip(rebuilt).zip(&magnitudes) {
+            for ((bin, estimate), &magnitude) in frame.iter_mut().zip(rebuilt_frame).zip(target) {
+                let norm = estimate.norm();
+                *bin = if norm > 0.0 { estimate * (magnitude / norm) } else { Complex::new(magnitude, 0.0) };
+            }
+        }
+        signal = overlap_add(&spectrum, &window, config.hop_length, c2r.as_ref())?;
+    }
+
+    // Undo the centre padding added before framing.
+    let start = config.frame_length / 2;
+    let end = (start + magnitudes.len() * config.hop_length).min(signal.len());
+    Ok(signal[start..end].to_vec())
+}
+
+/// Maps output-scale features back to linear mel power, keeping the first `n_mels` columns.
+fn invert_output_scale(mel_spectrogram: &[Vec<f32>], config: &MelConfig) -> Vec<Vec<f32>> {
+    let base = match config.log_base {
+        LogBase::Log10 => 10.0f32,
+        LogBase::Ln => std::f32::consts::E,
+        LogBase::Log2 => 2.0,
+    };
+    mel_spectrogram.iter()
+        .map(|frame| {
+            frame[..config.n_mels].iter()
+                .map(|&value| match config.output_scale {
+                    SpectrogramScale::Raw => value,
+                    SpectrogramScale::Log10 => match config.drc {
+                        Some(drc) => base.powf(value * drc.scale - drc.offset),
+                        None => base.powf(value),
+                    },
+                    SpectrogramScale::PowerDb => 10f32.powf(value / 10.0),
+                    SpectrogramScale::AmplitudeDb => 10f32.powf(value / 20.0),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Moore-Penrose pseudoinverse of the `[n_fft][n_mels]` filter bank as `[n_fft][n_mels]` rows,
+/// `F (FᵀF + λI)⁻¹`, with a small ridge so empty filters do not make the system singular.
+fn mel_pseudo_inverse(mel_filters: &MelFilterBank) -> Result<Vec<Vec<f32>>, MelError> {
+    let n_mels = mel_filters.n_mels();
+    let mut gram = vec![vec![0.0f64; n_mels]; n_mels];
+    for row in mel_filters.rows() {
+        for i in 0..n_mels {
+            for j in 0..n_mels {
+                gram[i][j] += row[i] as f64 * row[j] as f64;
+            }
+        }
+    }
+    let trace: f64 = (0..n_mels).map(|i| gram[i][i]).sum();
+    let ridge = (trace / n_mels.max(1) as f64).max(f64::MIN_POSITIVE) * 1e-6;
+    for (i, row) in gram.iter_mut().enumerate() {
+        row[i] += ridge;
+    }
+
+    // Gauss-Jordan elimination with partial pivoting.
+    let mut inverse: Vec<Vec<f64>> = (0..n_mels)
+        .map(|i| (0..n_mels).map(|j| if i == j { 1.0 } else { 0.0 }).collect())
+        .collect();
+    for col in 0..n_mels {
+        let pivot = (col..n_mels)
+            .max_by(|&a, &b| gram[a][col].abs().total_cmp(&gram[b][col].abs()))
+            .unwrap_or(col);
+        if gram[pivot][col].abs() < f64::EPSILON {
+            return Err(MelError::InvalidConfig("Mel filter bank is not invertible".to_string()));
+        }
+        gram.swap(col, pivot);
+        inverse.swap(col, pivot);
+        let scale = gram[col][col];
+        for j in 0..n_mels {
+            gram[col][j] /= scale;
+            inverse[col][j] /= scale;
+        }
+        for row in 0..n_mels {
+            if row != col {
+                let factor = gram[row][col];
+                for j in 0..n_mels {
+                    gram[row][j] -= factor * gram[col][j];
+                    inverse[row][j] -= factor * inverse[col][j];
+                }
+            }
+        }
+    }
+
+    Ok(mel_filters.rows()
+        .map(|row| {
+            (0..n_mels)
+                .map(|j| (0..n_mels).map(|i| row[i] as f64 * inverse[i][j]).sum::<f64>() as f32)
+                .collect()
+        })
+        .collect())
+}
+
+/// Inverse STFT by windowed overlap-add, normalised by the summed squared window.
+fn overlap_add(
+    spectrum: &[Vec<Complex<f32>>],
+    window: &[f32],
+    hop_length: usize,
+    c2r: &dyn ComplexToReal<f32>,
+) -> Result<Vec<f32>, MelError> {
+    let frame_length = window.len();
+    let len = spectrum.len().saturating_sub(1) * hop_length + frame_length;
+    let mut signal = vec![0.0; len];
+    let mut window_sum = vec![0.0; len];
+    let mut frame = c2r.make_output_vec();
+
+    for (i, bins) in spectrum.iter().enumerate() {
+        let mut bins = bins.clone();
+        // A real signal has purely real DC and Nyquist bins.
+        if let Some(dc) = bins.first_mut() {
+            dc.im = 0.0;
+        }
+        if frame_length.is_multiple_of(2) {
+            if let Some(nyquist) = bins.last_mut() {
+                nyquist.im = 0.0;
+            }
+        }
+        c2r.process(&mut bins, &mut frame)?;
+
+        let start = i * hop_length;
+        for (n, (&sample, &w)) in frame.iter().zip(window).enumerate() {
+            signal[start + n] += sample / frame_length as f32 * w;
+            window_sum[start + n] += w * w;
+        }
+    }
+
+    for (sample, norm) in signal.iter_mut().zip(window_sum) {
+        if norm > 1e-8 {
+            *sample /= norm;
+        }
+    }
+    Ok(signal)
+}
+
+#[cfg(all(feature = "std", not(feature = "wasm")))]
+pub fn extract_mel_spectrogram(path: &str, config: &MelConfig) -> Result<MelSpectrogram, MelError> {
+    MelExtractorContext::new(config.clone())?.extract(path)
+}
+
+/// Same as [`extract_mel_spectrogram`], reporting the completed fraction in `[0.0, 1.0]` to
+/// `progress` after resampling, the FFT, the mel projection and at the end.
+#[cfg(all(feature = "std", not(feature = "wasm")))]
+pub fn extract_mel_spectrogram_with_progress<F: Fn(f32) + Send>(
+    path: &str,
+    config: &MelConfig,
+    progress: F,
+) -> Result<MelSpectrogram, MelError> {
+    let context = MelExtractorContext::new(config.clone())?;
+    let (mono, orig_sample_rate) = read_audio(path, &config.downmix)?;
+    context.compute_mel_spectrogram_with_progress(mono, orig_sample_rate, &progress)
+}
+
+/// Same as [`extract_mel_spectrogram`], but decodes a WAV file already held in memory.
+pub fn extract_mel_spectrogram_from_bytes(bytes: &[u8], config: &MelConfig) -> Result<MelSpectrogram, MelError> {
+    MelExtractorContext::new(config.clone())?.extract_from_bytes(bytes)
+}
+
+/// Extracts MFCCs from a WAV file. The returned spectrogram holds `n_mfcc` coefficients per
+/// frame in place of mel bins.
+#[cfg(all(feature = "std", not(feature = "wasm")))]
+pub fn extract_mfcc(path: &str, config: &MelConfig, n_mfcc: usize) -> Result<MelSpectrogram, MelError> {
+    MelExtractorContext::new(config.clone())?.extract_mfcc(path, n_mfcc)
+}
+
+/// Extracts features for every file in `paths`, returning results in input order. Files are
+/// processed in parallel when the `parallel` feature is enabled.
+#[cfg(all(feature = "std", not(feature = "wasm")))]
+pub fn extract_batch(paths: &[&str], config: &MelConfig) -> Vec<Result<MelSpectrogram, MelError>> {
+    match MelExtractorContext::new(config.clone()) {
+        Ok(context) => context.extract_batch(paths, None),
+        Err(err) => {
+            let message = err.to_string();
+            paths.iter()
+                .map(|_| Err(MelError::InvalidConfig(message.clone())))
+                .collect()
+        }
+    }
+}
+
+/// Iterates over a long WAV file in windows of `config.max_duration_secs`,
+/// yielding one spectrogram per window instead of truncating the file. Consecutive windows
+/// share `overlap_samples` samples.
+pub struct MelStreamIterator<R: Read = BufReader<File>> {
+    context: MelExtractorContext,
+    reader: WavReader<R>,
+    window_len: usize,
+    overlap_len: usize,
+    buffer: Vec<f32>,
+    emitted: bool,
+    finished: bool,
+}
+
+#[cfg(all(feature = "std", not(feature = "wasm")))]
+impl MelStreamIterator<BufReader<File>> {
+    pub fn new(path: &str, config: MelConfig, overlap_samples: usize) -> Result<Self, MelError> {
+        MelStreamIterator::from_reader(WavReader::open(path)?, config, overlap_samples)
+    }
+}
+
+impl<R: Read> MelStreamIterator<R> {
+    pub fn from_reader(reader: WavReader<R>, config: MelConfig, overlap_samples: usize) -> Result<Self, MelError> {
+        let Some(max_samples) = config.max_samples() else {
+            return Err(MelError::InvalidConfig("Streaming needs max_duration_secs to size its windows".to_string()));
+        };
+        if overlap_samples >= max_samples {
+            return Err(MelError::InvalidConfig(format!(
+                "overlap_samples ({}) must be smaller than the window of {} samples",
+                overlap_samples, max_samples
+            )));
+        }
+
+        // Window sizes are given at the target rate; the buffer holds audio at the file's rate.
+        let orig_rate = reader.spec().sample_rate as u64;
+        let target_rate = config.sample_rate as u64;
+        let to_orig_rate = |n: usize| (n as u64 * orig_rate).div_ceil(target_rate) as usize;
+        let window_len = to_orig_rate(max_samples);
+        let overlap_len = to_orig_rate(overlap_samples);
+        // Rounding up can make the two meet when the file's rate is below the target rate.
+        if overlap_len >= window_len {
+            return Err(MelError::InvalidConfig(format!(
+                "overlap of {} samples leaves nothing to advance in a window of {} samples at the file's {} Hz",
+                overlap_len, window_len, orig_rate
+            )));
+        }
+
+        Ok(MelStreamIterator {
+            context: MelExtractorContext::new(config)?,
+            reader,
+            window_len,
+            overlap_len,
+            buffer: Vec::new(),
+            emitted: false,
+            finished: false,
+        })
+    }
+}
+
+impl<R: Read> Iterator for MelStreamIterator<R> {
+    type Item = Result<MelSpectrogram, MelError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let spec = self.reader.spec();
+        let needed = self.window_len - self.buffer.len();
+        let fresh = read_wav_samples(&mut self.reader, needed * spec.channels as usize)
+            .and_then(|samples| downmix(samples, spec.channels, &self.context.config.downmix));
+        let fresh = match fresh {
+            Ok(fresh) => fresh,
+            Err(err) => {
+                self.finished = true;
+                return Some(Err(err));
+            }
+        };
+
+        if fresh.is_empty() && (self.emitted || self.buffer.is_empty()) {
+            self.finished = true;
+            return None;
+        }
+        if fresh.len() < needed {
+            self.finished = true;
+        }
+
+        self.buffer.extend(fresh);
+        let chunk = self.buffer.clone();
+        let keep = self.overlap_len.min(self.buffer.len());
+        self.buffer.drain(..self.buffer.len() - keep);
+        self.emitted = true;
+
+        Some(self.context.compute_mel_spectrogram(chunk, spec.sample_rate))
+    }
+}
+
+/// One output frame of `n_mels` values.
+pub type MelFrame = Vec<f32>;
+
+/// Turns a live stream of mono samples at `config.sample_rate` into mel frames as soon as each
+/// one is complete, keeping only the last `frame_length` samples in a ring buffer.
+///
+/// Frames start every `hop_length` samples from the first pushed sample, with no centre padding,
+/// resampling, pre-emphasis, A-weighting, VAD, normalisation or deltas. Each frame is scaled on its own, so
+/// `SpectrogramScale::Log10` yields plain log-mel values without Whisper's dynamic range
+/// compression and the dB scales clip `top_db` below that frame's peak.
+///
+/// `new` returns `InvalidConfig` for a config that fails [`MelConfig::validate`], and
+/// `push_samples` returns `NanEncountered` when scaling a frame produces a NaN.
+pub struct RealTimeMelProcessor {
+    context: MelExtractorContext,
+    ring: Vec<f32>,
+    write_pos: usize,
+    samples_seen: u64,
+}
+
+impl RealTimeMelProcessor {
+    pub fn new(config: MelConfig) -> Result<Self, MelError> {
+        let ring = vec![0.0; config.frame_length];
+        Ok(RealTimeMelProcessor {
+            context: MelExtractorContext::new(config)?,
+            ring,
+            write_pos: 0,
+            samples_seen: 0,
+        })
+    }
+
+    /// Buffers `samples` and returns every frame completed by them, in order. Chunks of any
+    /// length are accepted; an incomplete frame waits for the next call.
+    pub fn push_samples(&mut self, samples: &[f32]) -> Result<Vec<MelFrame>, MelError> {
+        let frame_length = self.ring.len();
+        let hop_length = self.context.config.hop_length as u64;
+        let mut frames = Vec::new();
+
+        for &sample in samples {
+            self.ring[self.write_pos] = sample;
+            self.write_pos = (self.write_pos + 1) % frame_length;
+            self.samples_seen += 1;
+
+            let past_first_frame = self.samples_seen.checked_sub(frame_length as u64);
+            if past_first_frame.is_some_and(|n| n % hop_length == 0) {
+                // The oldest sample sits at the write position once the ring is full.
+                let mut frame = Vec::with_capacity(frame_length);
+                frame.extend_from_slice(&self.ring[self.write_pos..]);
+                frame.extend_from_slice(&self.ring[..self.write_pos]);
+                frames.push(self.context.compute_frame(frame)?);
+            }
+        }
+
+        Ok(frames)
+    }
+
+    /// Forgets all buffered audio so the next frame starts at the next pushed sample.
+    pub fn reset(&mut self) {
+        self.ring.fill(0.0);
+        self.write_pos = 0;
+        self.samples_seen = 0;
+    }
+}
+
+/// A [`RealTimeMelProcessor`] with a fixed latency of `hop_length` samples: the stream is
+/// preceded by `frame_length - hop_length` zeros, as librosa zero-pads the start of centred
+/// frames, so the first frame is emitted once `hop_length` samples have arrived and another
+/// after every further `hop_length`. Frame `t` ends at sample `(t + 1) * hop_length`.
+pub struct OnlineMelProcessor {
+    processor: RealTimeMelProcessor,
+}
+
+impl OnlineMelProcessor {
+    pub fn new(config: MelConfig) -> Result<Self, MelError> {
+        let mut online = OnlineMelProcessor { processor: RealTimeMelProcessor::new(config)? };
+        online.prime();
+        Ok(online)
+    }
+
+    /// Buffers `samples` and returns every frame completed by them, in order.
+    pub fn push_samples(&mut self, samples: &[f32]) -> Result<Vec<MelFrame>, MelError> {
+        self.processor.push_samples(samples)
+    }
+
+    /// Forgets all buffered audio and restores the zero look-ahead for a new stream.
+    pub fn reset(&mut self) {
+        self.processor.reset();
+        self.prime();
+    }
+
+    /// Counts `frame_length - hop_length` of the ring's zeros as already received.
+    fn prime(&mut self) {
+        let config = &self.processor.context.config;
+        let lookahead = config.frame_length - config.hop_length;
+        self.processor.write_pos = lookahead;
+        self.processor.samples_seen = lookahead as u64;
+    }
+}
+
+/// Holds the FFT plan, window and mel filter bank for a [`MelConfig`] so that many clips can
+/// be processed without recomputing them.
+#[derive(Clone)]
+pub struct MelExtractorContext {
+    config: MelConfig,
+    r2c: Arc<dyn RealToComplex<f32>>,
+    mel_filters: MelFilterBank,
+    window: Vec<f32>,
+}
+
+struct LogMelFeatures {
+    log_mel: Vec<Vec<f32>>,
+    log_energy: Option<Vec<f32>>,
+    zero_crossing_rate: Option<Vec<f32>>,
+    spectral_centroid: Option<Vec<f32>>,
+    spectral_flux: Option<Vec<f32>>,
+    f0: Option<Vec<Option<f32>>>,
+}
+
+impl MelExtractorContext {
+    pub fn new(config: MelConfig) -> Result<Self, MelError> {
+        config.validate()?;
+
+        let r2c = RealFftPlanner::<f32>::new().plan_fft_forward(config.frame_length);
+        let mel_filters = filter_bank(&config)?;
+        let window = make_window(config.frame_length, config.window_type);
+
+        Ok(MelExtractorContext {
+            config,
+            r2c,
+            mel_filters,
+            window,
+        })
+    }
+
+    pub fn config(&self) -> &MelConfig {
+        &self.config
+    }
+
+    #[cfg(all(feature = "std", not(feature = "wasm")))]
+    pub fn extract(&self, path: &str) -> Result<MelSpectrogram, MelError> {
+        let (mono, orig_sample_rate) = read_audio(path, &self.config.downmix)?;
+        self.compute_mel_spectrogram(mono, orig_sample_rate)
+    }
+
+    pub fn extract_from_bytes(&self, bytes: &[u8]) -> Result<MelSpectrogram, MelError> {
+        let (mono, orig_sample_rate) = read_wav_bytes(bytes, &self.config.downmix)?;
+        self.compute_mel_spectrogram(mono, orig_sample_rate)
+    }
+
+    /// Extracts features from mono `samples` recorded at `sample_rate`.
+    pub fn extract_from_samples(&self, samples: &[f32], sample_rate: u32) -> Result<MelSpectrogram, MelError> {
+        self.compute_mel_spectrogram(samples.to_vec(), sample_rate)
+    }
+
+    /// Extracts every file in `paths`, returning results in input order. With the `parallel`
+    /// feature, at most `max_threads` files are processed at once (`None` uses rayon's global
+    /// pool); without it, files are processed sequentially.
+    #[cfg(all(feature = "std", not(feature = "wasm")))]
+    pub fn extract_batch(&self, paths: &[&str], max_threads: Option<usize>) -> Vec<Result<MelSpectrogram, MelError>> {
+        #[cfg(feature = "parallel")]
+        {
+            let run = || paths.par_iter().map(|path| self.extract(path)).collect();
+            let pool = max_threads.and_then(|n| {
+                rayon::ThreadPoolBuilder::new().num_threads(n).build().ok()
+            });
+            match pool {
+                Some(pool) => pool.install(run),
+                None => run(),
+            }
+        }
+
+        #[cfg(not(feature = "parallel"))]
+        {
+            let _ = max_threads;
+            paths.iter().map(|path| self.extract(path)).collect()
+        }
+    }
+
+    #[cfg(all(feature = "std", not(feature = "wasm")))]
+    pub fn extract_mfcc(&self, path: &str, n_mfcc: usize) -> Result<MelSpectrogram, MelError> {
+        let (mono, orig_sample_rate) = read_audio(path, &self.config.downmix)?;
+        let features = self.compute_log_mel(mono, orig_sample_rate, &|_| {})?;
+        let mfcc = append_deltas(mel_to_mfcc(&features.log_mel, n_mfcc)?, self.config.delta_order)?;
+        let n_coeffs = mfcc.first().map_or(n_mfcc, Vec::len);
+
+        Ok(MelSpectrogram {
+            n_frames: mfcc.len(),
+            data: mfcc.into_iter().flatten().collect(),
+            n_mels: n_coeffs,
+            sample_rate: self.config.sample_rate,
+            hop_length: self.config.hop_length,
+            log_energy: features.log_energy,
+            zero_crossing_rate: features.zero_crossing_rate,
+            spectral_centroid: features.spectral_centroid,
+            spectral_flux: features.spectral_flux,
+            f0: features.f0,
+        })
+    }
+
+    /// Window, FFT, mel projection and output scaling of a single `frame_length` frame.
+    fn compute_frame(&self, frame: Vec<f32>) -> Result<Vec<f32>, MelError> {
+        let config = &self.config;
+        let windowed = apply_window(vec![frame], &self.window)?;
+        let rfft_spectrogram = apply_rfft(windowed, self.r2c.as_ref())?;
+        let spectrum = match config.spectrogram_type {
+            SpectrogramType::Power => power_spectrogram(rfft_spectrogram)?,
+            SpectrogramType::Amplitude => magnitude_spectrogram(rfft_spectrogram),
+        };
+        let mel_frame = self.mel_filters.project(&spectrum[0][..config.n_fft]);
+        let mut scaled = apply_output_scale(vec![mel_frame], config)?;
+        Ok(scaled.swap_remove(0))
+    }
+
+    fn compute_mel_spectrogram(&self, mono: Vec<f32>, orig_sample_rate: u32) -> Result<MelSpectrogram, MelError> {
+        self.compute_mel_spectrogram_with_progress(mono, orig_sample_rate, &|_| {})
+    }
+
+    /// Calls `progress` with the completed fraction after resampling, the FFT and the mel
+    /// projection, and with 1.0 once the spectrogram is ready.
+    fn compute_mel_spectrogram_with_progress(
+        &self,
+        mono: Vec<f32>,
+        orig_sample_rate: u32,
+        progress: &dyn Fn(f32),
+    ) -> Result<MelSpectrogram, MelError> {
+        let config = &self.config;
+        let features = self.compute_log_mel(mono, orig_sample_rate, progress)?;
+        let n_frames = features.log_mel.len();
+        let mut compressed = match config.drc {
+            Some(drc)
+                if config.output_scale == SpectrogramScale::Log10
+                    && !matches!(config.normalization, NormalizationMode::Pcen(_)) =>
+            {
+                apply_dynamic_range_compression(features.log_mel, &drc)?
+            }
+            _ => features.log_mel,
+        };
+        if let Some(mode) = config.cmvn {
+            apply_cmvn(&mut compressed, mode)?;
+        }
+        let mut final_spectrogram = append_deltas(compressed, config.delta_order)?;
+        match config.post_normalize {
+            PostNormalizeMode::None => {}
+            PostNormalizeMode::Frame => l2_normalize_frames(&mut final_spectrogram),
+            PostNormalizeMode::Global => l2_normalize_global(&mut final_spectrogram),
+        }
+        let n_mels = final_spectrogram.first().map_or(config.n_mels, Vec::len);
+        progress(1.0);
+
+        Ok(MelSpectrogram {
+            data: final_spectrogram.into_iter().flatten().collect(),
+            n_frames,
+            n_mels,
+            sample_rate: config.sample_rate,
+            hop_length: config.hop_length,
+            log_energy: features.log_energy,
+            zero_crossing_rate: features.zero_crossing_rate,
+            spectral_centroid: features.spectral_centroid,
+            spectral_flux: features.spectral_flux,
+            f0: features.f0,
+        })
+    }
+
+    fn compute_log_mel(
+        &self,
+        mono: Vec<f32>,
+        orig_sample_rate: u32,
+        progress: &dyn Fn(f32),
+    ) -> Result<LogMelFeatures, MelError> {
+        let config = &self.config;
+        #[cfg(feature = "augment")]
+        let mono = match config.noise_aug {
+            Some(noise) => {
+                let mut noisy = mono;
+                let mut rng = StdRng::seed_from_u64(noise.seed);
+                augment::add_gaussian_noise(&mut noisy, noise.snr_db, &mut rng);
+                noisy
+            }
+            None => mono,
+        };
+        let emphasized = match config.preemphasis_coeff {
+            Some(alpha) => apply_preemphasis(mono, alpha)?,
+            None => mono,
+        };
+        let resampled = resample_audio(emphasized, orig_sample_rate, config)?;
+        let shifted = match config.pitch_shift_semitones {
+            Some(semitones) => pitch_shift(&resampled, config.sample_rate, semitones)?,
+            None => resampled,
+        };
+        let stretched = match config.time_stretch_rate {
+            Some(rate) => {
+                let n_fft = vocoder_frame_length(config.sample_rate);
+                time_stretch(&shifted, rate, n_fft, n_fft / 4)?
+            }
+            None => shifted,
+        };
+        progress(0.3);
+        let voiced = match &config.vad {
+            Some(vad) => apply_vad(&stretched, vad),
+            None => stretched,
+        };
+        let trimmed = match config.trim_top_db {
+            Some(top_db) => trim_silence(&voiced, top_db, config.frame_length, config.hop_length).0,
+            None => voiced,
+        };
+        let weighted = if config.a_weighting {
+            apply_a_weighting(&trimmed, config.sample_rate)?
+        } else {
+            trimmed
+        };
+        let padded = pad_or_truncate(weighted, config)?;
+        let normalized = normalize(padded, config.normalization)?;
+        let framed = frame_signal(normalized, config)?;
+        let log_energy = config.include_log_energy.then(|| compute_log_energy(&framed));
+        let zero_crossing_rate = config.include_zero_crossing_rate.then(|| zero_crossing_rate(&framed));
+        let f0 = config.extract_f0.then(|| estimate_f0(&framed, config.sample_rate, MIN_F0, MAX_F0));
+        let windowed = apply_window(framed, &self.window)?;
+        let rfft_spectrogram = apply_rfft(windowed, self.r2c.as_ref())?;
+        progress(0.6);
+        let spectrum = match config.spectrogram_type {
+            SpectrogramType::Power => power_spectrogram(rfft_spectrogram)?,
+            SpectrogramType::Amplitude => magnitude_spectrogram(rfft_spectrogram),
+        };
+        // The spectral shape features are defined on the power spectrum whichever type feeds the
+        // mel filters.
+        let power_spec: Cow<[Vec<f32>]> = match config.spectrogram_type {
+            SpectrogramType::Power => Cow::Borrowed(&spectrum),
+            SpectrogramType::Amplitude => {
+                Cow::Owned(spectrum.iter().map(|frame| frame.iter().map(|m| m * m).collect()).collect())
+            }
+        };
+        let spectral_centroid = config.include_spectral_centroid.then(|| {
+            spectral_centroid(&power_spec, &fft_frequencies(config.n_fft, config.sample_rate))
+        });
+        let spectral_flux = config.include_spectral_flux.then(|| spectral_flux(&power_spec));
+        let mel_spectrogram = apply_mel_filters(&spectrum, &self.mel_filters, config)?;
+        progress(0.9);
+        let log_mel = apply_output_scale(mel_spectrogram, config)?;
+        let n_frames = log_mel.len();
+        let align = |mut track: Vec<f32>| {
+            track.truncate(n_frames);
+            track
+        };
+
+        Ok(LogMelFeatures {
+            log_mel,
+            log_energy: log_energy.map(align),
+            zero_crossing_rate: zero_crossing_rate.map(align),
+            spectral_centroid: spectral_centroid.map(align),
+            spectral_flux: spectral_flux.map(align),
+            f0: f0.map(|mut f0| {
+                f0.truncate(n_frames);
+                f0
+            }),
+        })
+    }
+}
+
+#[cfg(all(feature = "std", not(feature = "wasm")))]
+unsafe fn path_from_c<'a>(path: *const c_char) -> Option<&'a str> {
+    if path.is_null() {
+        eprintln!("Null path pointer");
+        return None;
+    }
+    match CStr::from_ptr(path).to_str() {
+        Ok(s) => Some(s),
+        Err(_) => {
+            eprintln!("Invalid UTF-8 path");
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "wasm"))]
+fn into_ffi_result(result: Result<MelSpectrogram, MelError>) -> MelSpectrogramData {
+    match result.and_then(MelSpectrogramData::try_from) {
+        Ok(data) => data,
+        Err(err) => {
+            eprintln!("{}", err);
+            MelSpectrogramData::default()
+        }
+    }
+}
+
+/// The returned buffer belongs to the caller; see [`MelSpectrogramData`] for how to release it.
+///
+/// # Safety
+///
+/// `path` must point to a valid NUL-terminated C string.
+#[cfg(all(feature = "std", not(feature = "wasm")))]
+#[no_mangle]
+#[must_use = "the spectrogram is freed as soon as it is dropped"]
+pub unsafe extern "C" fn extract_whisper_features(path: *const c_char) -> MelSpectrogramData {
+    let Some(path_str) = path_from_c(path) else {
+        return MelSpectrogramData::default();
+    };
+
+    into_ffi_result(extract_mel_spectrogram(path_str, &MelConfig::whisper_default()))
+}
+
+/// Extracts features with the parameters in `config`, or the Whisper defaults when `config` is
+/// null.
+///
+/// # Safety
+///
+/// `path` must point to a valid NUL-terminated C string and `config` must be null or point to a
+/// valid `CMelConfig`.
+#[cfg(all(feature = "std", not(feature = "wasm")))]
+#[no_mangle]
+pub unsafe extern "C" fn extract_mel_spectrogram_configured(
+    path: *const c_char,
+    config: *const CMelConfig,
+) -> MelSpectrogramData {
+    let Some(path_str) = path_from_c(path) else {
+        return MelSpectrogramData::default();
+    };
+    let config = config.as_ref().map_or_else(MelConfig::whisper_default, MelConfig::from);
+
+    into_ffi_result(extract_mel_spectrogram(path_str, &config))
+}
+
+/// Same as [`extract_mel_spectrogram_configured`], also reporting progress through the callback
+/// in `config`.
+///
+/// # Safety
+///
+/// `path` must point to a valid NUL-terminated C string and `config` must be null or point to a
+/// valid `CMelConfigV2` whose callback, if set, is safe to call with its user data.
+#[cfg(all(feature = "std", not(feature = "wasm")))]
+#[no_mangle]
+pub unsafe extern "C" fn extract_mel_spectrogram_configured_v2(
+    path: *const c_char,
+    config: *const CMelConfigV2,
+) -> MelSpectrogramData {
+    let Some(path_str) = path_from_c(path) else {
+        return MelSpectrogramData::default();
+    };
+    let Some(c_config) = config.as_ref() else {
+        return into_ffi_result(extract_mel_spectrogram(path_str, &MelConfig::whisper_default()));
+    };
+    let config = MelConfig::from(&c_config.config);
+    let result = MelExtractorContext::new(config.clone()).and_then(|context| {
+        let (mono, orig_sample_rate) = read_audio(path_str, &config.downmix)?;
+        context.compute_mel_spectrogram_with_progress(mono, orig_sample_rate, &|f| c_config.report_progress(f))
+    });
+
+    into_ffi_result(result)
+}
+
+/// # Safety
+///
+/// `data` must point to `len` readable bytes holding a complete WAV file.
+#[cfg(not(feature = "wasm"))]
+#[no_mangle]
+pub unsafe extern "C" fn extract_whisper_features_from_memory(data: *const u8, len: usize) -> MelSpectrogramData {
+    if data.is_null() {
+        eprintln!("Null WAV data pointer");
+        return MelSpectrogramData::default();
+    }
+    let bytes = slice::from_raw_parts(data, len);
+
+    into_ffi_result(extract_mel_spectrogram_from_bytes(bytes, &MelConfig::whisper_default()))
+}
+
+/// Extracts features from headerless PCM: `len` interleaved signed 16-bit two's-complement
+/// samples in native byte order (little-endian on every supported target), `channels` per
+/// frame. A null `config` uses the Whisper defaults.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable `i16` values and `config` must be null or point to a
+/// valid `CMelConfig`.
+#[cfg(not(feature = "wasm"))]
+#[no_mangle]
+pub unsafe extern "C" fn extract_from_raw_pcm(
+    data: *const i16,
+    len: usize,
+    sample_rate: u32,
+    channels: u16,
+    config: *const CMelConfig,
+) -> MelSpectrogramData {
+    if data.is_null() {
+        eprintln!("Null PCM data pointer");
+        return MelSpectrogramData::default();
+    }
+    let pcm = slice::from_raw_parts(data, len);
+    let config = config.as_ref().map_or_else(MelConfig::whisper_default, MelConfig::from);
+
+    into_ffi_result(extract_raw_pcm(pcm, sample_rate, channels, config, &|_| {}))
+}
+
+/// Same as [`extract_from_raw_pcm`], also reporting progress through the callback in `config`.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable `i16` values and `config` must be null or point to a
+/// valid `CMelConfigV2` whose callback, if set, is safe to call with its user data.
+#[cfg(not(feature = "wasm"))]
+#[no_mangle]
+pub unsafe extern "C" fn extract_from_raw_pcm_v2(
+    data: *const i16,
+    len: usize,
+    sample_rate: u32,
+    channels: u16,
+    config: *const CMelConfigV2,
+) -> MelSpectrogramData {
+    if data.is_null() {
+        eprintln!("Null PCM data pointer");
+        return MelSpectrogramData::default();
+    }
+    let pcm = slice::from_raw_parts(data, len);
+    let c_config = config.as_ref();
+    let config = c_config.map_or_else(MelConfig::whisper_default, |c_config| MelConfig::from(&c_config.config));
+    let progress = |fraction| {
+        if let Some(c_config) = c_config {
+            c_config.report_progress(fraction);
+        }
+    };
+
+    into_ffi_result(extract_raw_pcm(pcm, sample_rate, channels, config, &progress))
+}
+
+#[cfg(not(feature = "wasm"))]
+fn extract_raw_pcm(
+    pcm: &[i16],
+    sample_rate: u32,
+    channels: u16,
+    config: MelConfig,
+    progress: &dyn Fn(f32),
+) -> Result<MelSpectrogram, MelError> {
+    if sample_rate == 0 || channels == 0 {
+        return Err(MelError::InvalidAudio(format!(
+            "Invalid PCM format: {} Hz, {} channels",
+            sample_rate, channels
+        )));
+    }
+    let samples = pcm.iter().map(|&s| s as f32 / i16::MAX as f32).collect();
+    let mono = downmix(samples, channels, &config.downmix)?;
+    MelExtractorContext::new(config)?.compute_mel_spectrogram_with_progress(mono, sample_rate, progress)
+}
+
+/// Returns `n_mfcc` rows of cepstral coefficients (in the `n_mels` field) by `n_frames`
+/// columns. Free the result with `free_spectrogram_data`.
+///
+/// # Safety
+///
+/// `path` must point to a valid NUL-terminated C string.
+#[cfg(all(feature = "std", not(feature = "wasm")))]
+#[no_mangle]
+pub unsafe extern "C" fn extract_whisper_mfcc(path: *const c_char, n_mfcc: usize) -> MelSpectrogramData {
+    let Some(path_str) = path_from_c(path) else {
+        return MelSpectrogramData::default();
+    };
+
+    into_ffi_result(extract_mfcc(path_str, &MelConfig::whisper_default(), n_mfcc))
+}
+
+/// Whisper features plus the per-frame zero-crossing rate, spectral centroid and spectral flux.
+/// Free the result with `free_extended_spectrogram_data`.
+///
+/// # Safety
+///
+/// `path` must point to a valid NUL-terminated C string.
+#[cfg(all(feature = "std", not(feature = "wasm")))]
+#[no_mangle]
+pub unsafe extern "C" fn extract_whisper_features_extended(path: *const c_char) -> ExtendedMelSpectrogramData {
+    let Some(path_str) = path_from_c(path) else {
+        return ExtendedMelSpectrogramData::default();
+    };
+
+    let config = MelConfig {
+        include_zero_crossing_rate: true,
+        include_spectral_centroid: true,
+        include_spectral_flux: true,
+        ..MelConfig::whisper_default()
+    };
+    match extract_mel_spectrogram(path_str, &config).and_then(ExtendedMelSpectrogramData::try_from) {
+        Ok(data) => data,
+        Err(err) => {
+            eprintln!("{}", err);
+            ExtendedMelSpectrogramData::default()
+        }
+    }
+}
+
+/// # Safety
+///
+/// `spectrogram_data` must have been returned by this library and not freed before.
+#[cfg(not(feature = "wasm"))]
+#[no_mangle]
+pub unsafe extern "C" fn free_spectrogram_data(spectrogram_data: MelSpectrogramData) {
+    drop(spectrogram_data);
+}
+
+/// # Safety
+///
+/// `extended_data` must have been returned by this library and not freed before.
+#[cfg(not(feature = "wasm"))]
+#[no_mangle]
+pub unsafe extern "C" fn free_extended_spectrogram_data(extended_data: ExtendedMelSpectrogramData) {
+    free_spectrogram_data(extended_data.mel);
+    free_raw_buffer(extended_data.zcr, extended_data.n_zcr);
+    free_raw_buffer(extended_data.spectral_centroid, extended_data.n_spectral_centroid);
+    free_raw_buffer(extended_data.spectral_flux, extended_data.n_spectral_flux);
+}
+
+}
+
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    // These exercise the raw-pointer ownership hand-off and are meant to stay clean under
+    // `cargo +nightly miri test --lib ffi_`.
+
+    #[test]
+    #[cfg(not(feature = "wasm"))]
+    fn ffi_spectrogram_data_round_trips_through_free() {
+        let spectrogram = MelSpectrogram::from_parts((0..6).map(|i| i as f32).collect(), 3, 2, 16000, 160).unwrap();
+        let data = MelSpectrogramData::try_from(spectrogram).unwrap();
+        assert_eq!((data.n_frames, data.n_mels), (3, 2));
+        // SAFETY: `data` points at the 6 mel-major values just allocated above.
+        let values = unsafe { std::slice::from_raw_parts(data.data, 6) };
+        assert_eq!(values, [0.0, 2.0, 4.0, 1.0, 3.0, 5.0]);
+        // SAFETY: `data` came from this library and is freed exactly once.
+        unsafe { free_spectrogram_data(data) };
+    }
+
+    #[test]
+    #[cfg(not(feature = "wasm"))]
+    fn ffi_free_accepts_null_data() {
+        let data = MelSpectrogramData { data: ptr::null_mut(), n_frames: 3, n_mels: 80 };
+        // SAFETY: a null buffer is never dereferenced or deallocated.
+        unsafe { free_spectrogram_data(data) };
+        // SAFETY: as above, for every default (null) track.
+        unsafe { free_extended_spectrogram_data(ExtendedMelSpectrogramData::default()) };
+    }
+
+    #[test]
+    #[cfg(not(feature = "wasm"))]
+    fn ffi_free_accepts_zero_length_buffer() {
+        let spectrogram = MelSpectrogram::from_parts(Vec::new(), 0, 80, 16000, 160).unwrap();
+        let data = MelSpectrogramData::try_from(spectrogram).unwrap();
+        assert_eq!(data.n_frames * data.n_mels, 0);
+        // SAFETY: `data` came from this library and is freed exactly once.
+        unsafe { free_spectrogram_data(data) };
+    }
+
+    #[test]
+    #[cfg(not(feature = "wasm"))]
+    fn ffi_extended_data_frees_every_track() {
+        let mut spectrogram = MelSpectrogram::from_parts(vec![1.0; 8], 4, 2, 16000, 160).unwrap();
+        spectrogram.zero_crossing_rate = Some(vec![0.5; 4]);
+        spectrogram.spectral_flux = Some(Vec::new());
+        let data = ExtendedMelSpectrogramData::try_from(spectrogram).unwrap();
+        assert!(data.spectral_centroid.is_null());
+        // SAFETY: `data` came from this library and is freed exactly once.
+        unsafe { free_extended_spectrogram_data(data) };
+    }
+
+    fn sine_wav_bytes(n_samples: usize, sample_rate: u32) -> Vec<u8> {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut cursor = std::io::Cursor::new(Vec::new());
+        let mut writer = hound::WavWriter::new(&mut cursor, spec).unwrap();
+        for i in 0..n_samples {
+            let sample = 0.5 * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / sample_rate as f32).sin();
+            writer.write_sample((sample * i16::MAX as f32) as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+        cursor.into_inner()
+    }
+
+    #[test]
+    fn short_clip_with_more_than_80_mels_converts_to_mel_major() {
+        let config = MelConfig::builder().n_mels(128).max_duration_secs(None).build().unwrap();
+        let spectrogram = extract_mel_spectrogram_from_bytes(&sine_wav_bytes(16000, 16000), &config).unwrap();
+        let (n_frames, n_mels) = (spectrogram.n_frames(), spectrogram.n_mels());
+        assert!(n_frames < 3000, "expected a short clip, got {} frames", n_frames);
+        assert_eq!(n_mels, 128);
+
+        let expected: Vec<f32> = (0..n_mels)
+            .flat_map(|mel| (0..n_frames).map(move |frame| (mel, frame)))
+            .map(|(mel, frame)| spectrogram.get_frame(frame)[mel])
+            .collect();
+        let data = MelSpectrogramData::try_from(spectrogram).unwrap();
+        assert_eq!((data.n_frames, data.n_mels), (n_frames, n_mels));
+        // SAFETY: `data` owns `n_frames * n_mels` values and is dropped after this borrow.
+        let values = unsafe { std::slice::from_raw_parts(data.data, n_frames * n_mels) };
+        assert_eq!(values, expected.as_slice());
+    }
+
+    #[test]
+    fn transpose_rejects_jagged_rows() {
+        let result = transpose(vec![vec![1.0, 2.0, 3.0], vec![4.0, 5.0]]);
+        assert!(matches!(result, Err(MelError::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn transpose_swaps_rows_and_columns() {
+        let transposed = transpose(vec![vec![1.0, 2.0, 3.0], vec![4.0, 5.0, 6.0]]).unwrap();
+        assert_eq!(transposed, vec![vec![1.0, 4.0], vec![2.0, 5.0], vec![3.0, 6.0]]);
+    }
+
+    fn sine(n_samples: usize, sample_rate: u32, frequency: f32) -> Vec<f32> {
+        (0..n_samples)
+            .map(|i| 0.5 * (2.0 * std::f32::consts::PI * frequency * i as f32 / sample_rate as f32).sin())
+            .collect()
+    }
+
+    #[test]
+    fn griffin_lim_sine_round_trip_keeps_mel_snr_above_20_db() {
+        let config = MelConfig::builder()
+            .max_duration_secs(None)
+            .output_scale(SpectrogramScale::Raw)
+            .drc(None)
+            .top_db(None)
+            .build()
+            .unwrap();
+        let context = MelExtractorContext::new(config.clone()).unwrap();
+        let mel_frames = |spectrogram: MelSpectrogram| -> Vec<Vec<f32>> {
+            (0..spectrogram.n_frames()).map(|i| spectrogram.get_frame(i).to_vec()).collect()
+        };
+
+        let original = mel_frames(context.extract_from_samples(&sine(16000, 16000, 440.0), 16000).unwrap());
+        // Zero-phase Griffin-Lim needs on the order of a hundred iterations to settle on a pure tone.
+        let audio = griffin_lim(&original, &config, 200).unwrap();
+        let reconstructed = mel_frames(context.extract_from_samples(&audio, 16000).unwrap());
+
+        // Phase is only recovered up to a shift, so compare in the mel power domain, skipping
+        // the edge frames where the reconstruction has no overlapping neighbours.
+        let n_frames = original.len().min(reconstructed.len());
+        let (mut signal, mut noise) = (0.0f64, 0.0f64);
+        for (a, b) in original[2..n_frames - 2].iter().zip(&reconstructed[2..n_frames - 2]) {
+            for (&x, &y) in a.iter().zip(b) {
+                signal += (x as f64).powi(2);
+                noise += (x as f64 - y as f64).powi(2);
+            }
+        }
+        let snr_db = 10.0 * (signal / noise).log10();
+        assert!(snr_db > 20.0, "mel SNR was {:.1} dB", snr_db);
+    }
+
+    #[test]
+    fn stream_rejects_overlap_that_rounds_up_to_the_window() {
+        // 2 target samples at 16 kHz become 1 sample at 4 kHz, and so does the 1-sample overlap.
+        let config = MelConfig::builder().max_duration_secs(Some(2.0 / 16000.0)).build().unwrap();
+        let reader = hound::WavReader::new(std::io::Cursor::new(sine_wav_bytes(400, 4000))).unwrap();
+        let result = MelStreamIterator::from_reader(reader, config, 1);
+        assert!(matches!(result, Err(MelError::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn a_weighting_attenuates_100_hz_relative_to_1_khz() {
+        let rms = |samples: &[f32]| (samples.iter().map(|x| x * x).sum::<f32>() / samples.len() as f32).sqrt();
+        // Skip the first 100 ms while the filter settles.
+        let weighted_rms = |frequency| rms(&apply_a_weighting(&sine(16000, 16000, frequency), 16000).unwrap()[1600..]);
+        let input_rms = rms(&sine(16000, 16000, 1000.0));
+
+        let gain_1khz_db = 20.0 * (weighted_rms(1000.0) / input_rms).log10();
+        let gain_100hz_db = 20.0 * (weighted_rms(100.0) / input_rms).log10();
+        assert!(gain_1khz_db.abs() < 0.1, "1 kHz gain was {:.2} dB", gain_1khz_db);
+        // IEC 61672-1 puts 100 Hz at -19.1 dB.
+        assert!((gain_100hz_db + 19.1).abs() < 0.5, "100 Hz gain was {:.2} dB", gain_100hz_db);
+    }
+
+    #[test]
+    fn a_weighting_rejects_sample_rates_without_1_khz() {
+        for sample_rate in [0, 2000] {
+            assert!(matches!(apply_a_weighting(&[0.0; 16], sample_rate), Err(MelError::InvalidConfig(_))));
+        }
+    }
+
+    #[test]
+    fn c_mel_config_v2_extends_the_frozen_v1_layout() {
+        assert_eq!(std::mem::offset_of!(CMelConfigV2, config), 0);
+        assert!(std::mem::offset_of!(CMelConfigV2, progress) >= std::mem::size_of::<CMelConfig>());
+    }
+
+    #[test]
+    #[cfg(not(feature = "wasm"))]
+    fn raw_pcm_v2_reports_progress_through_the_callback() {
+        extern "C" fn record(fraction: f32, user_data: *mut std::ffi::c_void) {
+            // SAFETY: the test passes a pointer to a live `Vec<f32>`.
+            unsafe { (*user_data.cast::<Vec<f32>>()).push(fraction) };
+        }
+        let mut fractions: Vec<f32> = Vec::new();
+        let config = CMelConfigV2 {
+            config: CMelConfig {
+                sample_rate: 16000,
+                n_mels: 80,
+                n_fft: 201,
+                hop_length: 160,
+                frame_length: 400,
+                min_freq: 0.0,
+                max_freq: 8000.0,
+                use_slaney_norm: 1,
+            },
+            progress: Some(record),
+            progress_user_data: (&mut fractions as *mut Vec<f32>).cast(),
+        };
+        let pcm: Vec<i16> = sine(1600, 16000, 440.0).iter().map(|&x| (x * i16::MAX as f32) as i16).collect();
+        // SAFETY: `pcm` holds `pcm.len()` samples and `config` outlives the call.
+        let data = unsafe { extract_from_raw_pcm_v2(pcm.as_ptr(), pcm.len(), 16000, 1, &config) };
+        assert!(!data.data.is_null());
+        assert_eq!(fractions.last(), Some(&1.0));
+        assert!(fractions.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+
+    #[test]
+    fn real_time_processor_matches_across_chunk_sizes() {
+        let config = MelConfig::builder().max_duration_secs(None).build().unwrap();
+        let samples = sine(4000, 16000, 440.0);
+
+        let mut whole = RealTimeMelProcessor::new(config.clone()).unwrap();
+        let expected = whole.push_samples(&samples).unwrap();
+        assert_eq!(expected.len(), 1 + (samples.len() - config.frame_length) / config.hop_length);
+
+        let mut chunked = RealTimeMelProcessor::new(config.clone()).unwrap();
+        assert!(chunked.push_samples(&samples[..config.frame_length - 1]).unwrap().is_empty());
+        let mut frames = Vec::new();
+        for chunk in samples[config.frame_length - 1..].chunks(7) {
+            frames.extend(chunked.push_samples(chunk).unwrap());
+        }
+        assert_eq!(frames, expected);
+    }
+
+    #[test]
+    fn real_time_processor_rejects_invalid_config() {
+        let config = MelConfig { hop_length: 0, ..MelConfig::whisper_default() };
+        assert!(matches!(RealTimeMelProcessor::new(config), Err(MelError::InvalidConfig(_))));
+    }
+
+    /// SplitMix64, so the property tests below are reproducible without extra dependencies.
+    struct SplitMix64(u64);
+
+    impl SplitMix64 {
+        fn next(&mut self) -> u64 {
+            self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = self.0;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^ (z >> 31)
+        }
+
+        fn below(&mut self, n: u64) -> usize {
+            (self.next() % n) as usize
+        }
+    }
+
+    /// A spectrogram of random shape, sample rate and hop length. With `finite_only` unset the
+    /// values are arbitrary bit patterns, including NaNs, infinities and subnormals.
+    fn arbitrary_spectrogram(rng: &mut SplitMix64, finite_only: bool) -> MelSpectrogram {
+        let (n_frames, n_mels) = (rng.below(40), 1 + rng.below(128));
+        let data = (0..n_frames * n_mels)
+            .map(|_| loop {
+                let value = f32::from_bits(rng.next() as u32);
+                if !finite_only || value.is_finite() {
+                    break value;
+                }
+            })
+            .collect();
+        let sample_rate = rng.next() as u32;
+        let hop_length = 1 + rng.below(1024);
+        MelSpectrogram::from_parts(data, n_frames, n_mels, sample_rate, hop_length).unwrap()
+    }
+
+    fn bits(spec: &MelSpectrogram) -> Vec<u32> {
+        spec.as_slice().iter().map(|value| value.to_bits()).collect()
+    }
+
+    #[test]
+    fn binary_round_trip_is_bit_exact_for_arbitrary_spectrograms() {
+        let mut rng = SplitMix64(0x4D45_4C53);
+        for _ in 0..256 {
+            let spec = arbitrary_spectrogram(&mut rng, false);
+            let mut bytes = Vec::new();
+            save_spectrogram_binary(&spec, &mut bytes).unwrap();
+            assert_eq!(bytes.len(), 16 + 4 * spec.as_slice().len());
+
+            let loaded = load_spectrogram_binary(&mut bytes.as_slice()).unwrap();
+            assert_eq!((loaded.n_frames(), loaded.n_mels()), (spec.n_frames(), spec.n_mels()));
+            assert_eq!(loaded.sample_rate(), spec.sample_rate());
+            assert_eq!(bits(&loaded), bits(&spec));
+        }
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_round_trip_is_bit_exact_for_arbitrary_finite_spectrograms() {
+        // JSON has no NaN or infinity, so only finite values are generated.
+        let mut rng = SplitMix64(0x4A53_4F4E);
+        for _ in 0..256 {
+            let spec = arbitrary_spectrogram(&mut rng, true);
+            let loaded = spectrogram_from_json(&spectrogram_to_json(&spec).unwrap()).unwrap();
+            assert_eq!((loaded.n_frames(), loaded.n_mels()), (spec.n_frames(), spec.n_mels()));
+            assert_eq!((loaded.sample_rate(), loaded.hop_length()), (spec.sample_rate(), spec.hop_length()));
+            assert_eq!(bits(&loaded), bits(&spec));
+        }
+    }
+
+    #[cfg(feature = "tfrecord")]
+    #[test]
+    fn masked_crc32c_matches_the_castagnoli_check_value() {
+        // CRC-32C("123456789") is 0xE3069283; masking rotates it right by 15 and adds 0xA282EAD8.
+        assert_eq!(masked_crc32c(b"123456789"), 0xE306_9283u32.rotate_right(15).wrapping_add(0xA282_EAD8));
+    }
+
+    #[cfg(feature = "tfrecord")]
+    #[test]
+    fn tfrecord_of_a_1x1_spectrogram_is_byte_exact() {
+        #[rustfmt::skip]
+        const EXPECTED: [u8; 83] = [
+            // Record length (67, u64) and its masked CRC-32C.
+            0x43, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xb1, 0x85, 0x25, 0xa0,
+            // Example { features: Features {
+            0x0a, 0x41, 0x0a, 0x1b,
+            //   "mel_spectrogram": Feature { float_list: FloatList { value: [1.5] } }
+            0x0a, 0x0f, 0x6d, 0x65, 0x6c, 0x5f, 0x73, 0x70, 0x65, 0x63, 0x74, 0x72, 0x6f, 0x67, 0x72, 0x61,
+            0x6d, 0x12, 0x08, 0x12, 0x06, 0x0a, 0x04, 0x00, 0x00, 0xc0, 0x3f,
+            //   "n_frames": Feature { int64_list: Int64List { value: [1] } }
+            0x0a, 0x11, 0x0a, 0x08, 0x6e, 0x5f, 0x66, 0x72, 0x61, 0x6d, 0x65, 0x73, 0x12, 0x05, 0x1a, 0x03,
+            0x0a, 0x01, 0x01,
+            //   "n_mels": Feature { int64_list: Int64List { value: [1] } }
+            0x0a, 0x0f, 0x0a, 0x06, 0x6e, 0x5f, 0x6d, 0x65, 0x6c, 0x73, 0x12, 0x05, 0x1a, 0x03, 0x0a, 0x01,
+            0x01,
+            // Masked CRC-32C of the Example.
+            0x31, 0x57, 0x69, 0x2f,
+        ];
+        let spec = MelSpectrogram::from_parts(vec![1.5], 1, 1, 16000, 160).unwrap();
+        let path = std::env::temp_dir().join(format!("mel-1x1-{}.tfrecord", std::process::id()));
+        save_tfrecord(&spec, path.to_str().unwrap()).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(bytes, EXPECTED);
+    }
+
+    #[test]
+    fn slaney_and_htk_scales_agree_at_1_khz() {
+        // Both scales are anchored at 1 kHz; Slaney counts in 200/3 Hz steps (15 units there), so
+        // rescale it to the HTK convention of 1000 mel at 1000 Hz before comparing.
+        let htk = MelScale::Htk.hertz_to_mel(1000.0);
+        let slaney = MelScale::Slaney.hertz_to_mel(1000.0) * 1000.0 / 15.0;
+        assert!((htk - slaney).abs() < 0.5, "HTK {} vs Slaney {}", htk, slaney);
+    }
+
+    /// Power of the `frequency` component of `samples`, as a single DFT bin.
+    fn tone_power(samples: &[f32], sample_rate: u32, frequency: f32) -> f32 {
+        let (re, im) = samples.iter().enumerate().fold((0.0f64, 0.0f64), |(re, im), (i, &x)| {
+            let phase = 2.0 * std::f64::consts::PI * frequency as f64 * i as f64 / sample_rate as f64;
+            (re + x as f64 * phase.cos(), im - x as f64 * phase.sin())
+        });
+        (re * re + im * im) as f32
+    }
+
+    #[test]
+    fn preemphasis_boosts_high_tone_relative_to_low_tone() {
+        let two_tone: Vec<f32> = sine(16000, 16000, 200.0)
+            .iter()
+            .zip(sine(16000, 16000, 3000.0))
+            .map(|(low, high)| low + high)
+            .collect();
+        let emphasized = apply_preemphasis(two_tone.clone(), 0.97).unwrap();
+
+        let ratio = |samples: &[f32]| tone_power(samples, 16000, 3000.0) / tone_power(samples, 16000, 200.0);
+        let before = ratio(&two_tone);
+        let after = ratio(&emphasized);
+        assert!((before - 1.0).abs() < 1e-3, "tones should start balanced, got {}", before);
+        assert!(after > 10.0 * before, "pre-emphasis raised the high/low ratio only from {} to {}", before, after);
+    }
+
+    #[test]
+    fn reflect_padding_leaves_no_dc_in_the_first_frame() {
+        // Start the sine at a crest so the mirrored edge continues it smoothly; zero padding
+        // instead cuts it in half and leaks into the DC bin.
+        let samples: Vec<f32> = (0..16000)
+            .map(|i| 0.5 * (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / 16000.0 + std::f32::consts::FRAC_PI_2).sin())
+            .collect();
+        let first_frame_dc = |padding_mode: PaddingMode| -> f32 {
+            let config = MelConfig::builder().max_duration_secs(None).padding_mode(padding_mode).build().unwrap();
+            let padded = pad_or_truncate(samples.clone(), &config).unwrap();
+            let mut frame: Vec<f32> = padded[..config.frame_length]
+                .iter()
+                .zip(make_window(config.frame_length, config.window_type))
+                .map(|(x, w)| x * w)
+                .collect();
+            let r2c = RealFftPlanner::<f32>::new().plan_fft_forward(config.frame_length);
+            let mut spectrum = r2c.make_output_vec();
+            r2c.process(&mut frame, &mut spectrum).unwrap();
+            spectrum[0].norm()
+        };
+
+        let reflect = first_frame_dc(PaddingMode::Reflect);
+        let zero = first_frame_dc(PaddingMode::Zero);
+        assert!(reflect < 1e-3, "reflect padding left a DC component of {}", reflect);
+        assert!(zero > 0.1, "zero padding should leak into DC, got {}", zero);
+    }
+
+    #[test]
+    fn zero_crossing_rate_of_dc_is_zero() {
+        assert_eq!(zero_crossing_rate(&[vec![0.3; 400], vec![-0.3; 400]]), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn zero_crossing_rate_of_alternating_square_wave_is_one() {
+        let square: Vec<f32> = (0..400).map(|i| if i % 2 == 0 { 1.0 } else { -1.0 }).collect();
+        assert_eq!(zero_crossing_rate(&[square]), [1.0]);
+    }
+
+    #[test]
+    fn power_to_db_of_unit_power_at_unit_reference_is_zero() {
+        let mut spectrogram = vec![vec![1.0; 80]; 10];
+        power_to_db(&mut spectrogram, 1.0, 1e-10, Some(80.0));
+        assert!(spectrogram.iter().flatten().all(|&db| db == 0.0));
+    }
+
+    #[test]
+    fn spectral_centroid_of_a_1_khz_sine_is_1_khz() {
+        let config = MelConfig::builder().max_duration_secs(None).include_spectral_centroid(true).build().unwrap();
+        let context = MelExtractorContext::new(config).unwrap();
+        let spectrogram = context.extract_from_samples(&sine(16000, 16000, 1000.0), 16000).unwrap();
+        let centroid = spectrogram.spectral_centroid().unwrap();
+        assert_eq!(centroid.len(), spectrogram.n_frames());
+        // Frames reaching into the zero padding at either end see a truncated, broadband tone.
+        let pad = 400 / 2;
+        let interior = centroid.iter().enumerate().filter(|(i, _)| i * 160 >= pad && i * 160 + 400 <= pad + 16000);
+        for (i, &hz) in interior {
+            assert!((hz - 1000.0).abs() < 10.0, "frame {}: centroid {} Hz", i, hz);
+        }
+    }
+
+    #[test]
+    fn spectral_rolloff_of_energy_in_the_first_bin_is_its_frequency() {
+        let fft_freqs = fft_frequencies(201, 16000);
+        let mut frame = vec![0.0; 201];
+        frame[0] = 1.0;
+        for roll_percent in [0.5, 0.85, 1.0] {
+            assert_eq!(spectral_rolloff(&[frame.clone()], &fft_freqs, roll_percent).unwrap(), [fft_freqs[0]]);
+        }
+    }
+
+    #[test]
+    fn spectral_rolloff_rejects_roll_percent_outside_unit_interval() {
+        let fft_freqs = fft_frequencies(201, 16000);
+        for roll_percent in [0.0, -0.5, 1.01, f32::NAN] {
+            let result = spectral_rolloff(&[vec![1.0; 201]], &fft_freqs, roll_percent);
+            assert!(matches!(result, Err(MelError::InvalidConfig(_))), "accepted {}", roll_percent);
+        }
+    }
+
+    /// `n_frames` mono 48 kHz, 128 kbps MPEG-1 Layer I frames carrying a tone at the centre of
+    /// subband 2, 1875 Hz. No Layer III encoder is available to the test suite, and minimp3
+    /// decodes Layer I through the same `Decoder` path that `read_mp3` uses.
+    #[cfg(feature = "mp3")]
+    fn mpeg1_layer1_tone(n_frames: usize) -> Vec<u8> {
+        const FRAME_BYTES: usize = 128;
+        const SUBBAND: usize = 2;
+        let mut stream = Vec::with_capacity(n_frames * FRAME_BYTES);
+        for _ in 0..n_frames {
+            let mut frame = vec![0u8; FRAME_BYTES];
+            let mut pos = 0;
+            let mut put = |value: u32, width: usize| {
+                for bit in (0..width).rev() {
+                    frame[pos / 8] |= (((value >> bit) & 1) as u8) << (7 - pos % 8);
+                    pos += 1;
+                }
+            };
+            // Sync, MPEG-1, Layer I, no CRC; 128 kbps, 48 kHz, no padding; mono.
+            put(0xFFFF_44C0, 32);
+            for subband in 0..32 {
+                put(if subband == SUBBAND { 1 } else { 0 }, 4);
+            }
+            put(6, 6);
+            // Two-bit samples decode as `code - 1`; cycling 1, 0, -1, 0 puts the tone at a
+            // quarter of the subband rate, which the synthesis bank maps to the band centre.
+            for code in [2, 1, 0, 1].repeat(3) {
+                put(code, 2);
+            }
+            stream.extend(frame);
+        }
+        stream
+    }
+
+    #[cfg(feature = "mp3")]
+    #[test]
+    fn mp3_tone_keeps_its_frequency_through_decode_and_resampling() {
+        let path = std::env::temp_dir().join(format!("mel-tone-{}.mp3", std::process::id()));
+        std::fs::write(&path, mpeg1_layer1_tone(125)).unwrap();
+        let decoded = read_mp3(path.to_str().unwrap(), &DownmixStrategy::Average);
+        std::fs::remove_file(&path).unwrap();
+
+        let (samples, sample_rate) = decoded.unwrap();
+        assert_eq!(sample_rate, 48000);
+        assert_eq!(samples.len(), 125 * 384 - MP3_DECODER_DELAY);
+        let config = MelConfig::builder().max_duration_secs(None).build().unwrap();
+        let resampled = resample_audio(samples, sample_rate, &config).unwrap();
+
+        // Search a 0.1 Hz grid around the tone for the strongest DFT component.
+        let peak = (18250..=19250)
+            .map(|decihertz| decihertz as f32 / 10.0)
+            .max_by(|&a, &b| tone_power(&resampled, 16000, a).total_cmp(&tone_power(&resampled, 16000, b)))
+            .unwrap();
+        assert!((peak - 1875.0).abs() < 2.0, "decoded tone at {} Hz", peak);
+    }
+
+    #[test]
+    #[cfg(not(feature = "wasm"))]
+    fn save_wav_f32_round_trips_within_epsilon() {
+        let samples: Vec<f32> = sine(16000, 16000, 440.0).iter().map(|s| s * 1.7 + 1e-7).collect();
+        let path = std::env::temp_dir().join(format!("mel-f32-{}.wav", std::process::id()));
+        let path = path.to_str().unwrap();
+        save_wav_f32(path, &samples, 16000).unwrap();
+        let loaded = read_audio(path, &DownmixStrategy::Average);
+        std::fs::remove_file(path).unwrap();
+
+        let (loaded, sample_rate) = loaded.unwrap();
+        assert_eq!((loaded.len(), sample_rate), (samples.len(), 16000));
+        for (i, (a, b)) in samples.iter().zip(&loaded).enumerate() {
+            assert!((a - b).abs() <= f32::EPSILON, "sample {}: wrote {}, read {}", i, a, b);
+        }
+    }
+
+    #[test]
+    fn stack_context_places_neighbours_at_their_offsets() {
+        let (n_frames, n_features, left, right) = (6, 3, 2, 1);
+        let features: Vec<Vec<f32>> =
+            (0..n_frames).map(|t| (0..n_features).map(|f| (10 * t + f) as f32).collect()).collect();
+        let stacked = stack_context(&features, left, right);
+
+        assert_eq!(stacked.len(), n_frames);
+        for (t, frame) in stacked.iter().enumerate() {
+            assert_eq!(frame.len(), n_features * (left + 1 + right));
+            assert_eq!(&frame[n_features * left..n_features * (left + 1)], features[t].as_slice());
+            for (slot, chunk) in frame.chunks(n_features).enumerate() {
+                let source = (t + slot).saturating_sub(left).min(n_frames - 1);
+                assert_eq!(chunk, features[source].as_slice(), "frame {} slot {}", t, slot);
+            }
+        }
+        // The edges replicate the first and last frames.
+        assert_eq!(stacked[0][..n_features * left], features[0].repeat(left));
+        assert_eq!(stacked[n_frames - 1][n_features * (left + 1)..], features[n_frames - 1].repeat(right));
+    }
+
+    #[test]
+    fn disabling_drc_leaves_the_log_output_unchanged() {
+        let samples = sine(16000, 16000, 440.0);
+        let frames = |config: MelConfig| -> Vec<Vec<f32>> {
+            let spectrogram = MelExtractorContext::new(config).unwrap().extract_from_samples(&samples, 16000).unwrap();
+            (0..spectrogram.n_frames()).map(|t| spectrogram.get_frame(t).to_vec()).collect()
+        };
+        let builder = || MelConfig::builder().max_duration_secs(None);
+
+        let config = builder().drc(None).build().unwrap();
+        let raw = frames(builder().output_scale(SpectrogramScale::Raw).build().unwrap());
+        let expected = apply_log(raw, config.log_base, config.log_floor).unwrap();
+        assert_eq!(frames(config), expected);
+        assert_ne!(frames(builder().drc(Some(DrcConfig::whisper())).build().unwrap()), expected);
+    }
+
+    #[test]
+    fn wiener_filter_raises_signal_band_mel_energy_relative_to_noise_at_0_db_snr() {
+        let config = MelConfig::builder().max_duration_secs(None).build().unwrap();
+        let window = make_window(config.frame_length, config.window_type);
+        let r2c = RealFftPlanner::<f32>::new().plan_fft_forward(config.frame_length);
+        let power = |samples: Vec<f32>| -> Vec<Vec<f32>> {
+            let frames = apply_window(frame_signal(samples, &config).unwrap(), &window).unwrap();
+            power_spectrogram(apply_rfft(frames, r2c.as_ref()).unwrap()).unwrap()
+        };
+        // Uniform white noise with the sine's power of 0.125.
+        let mut rng = SplitMix64(82);
+        let mut noise = |n: usize| -> Vec<f32> {
+            (0..n).map(|_| ((rng.next() >> 11) as f32 / (1u64 << 53) as f32 * 2.0 - 1.0) * 0.375f32.sqrt()).collect()
+        };
+        let tone = sine(16000, 16000, 1000.0);
+        let noise_estimate = estimate_noise_power(&power(noise(16000)), usize::MAX);
+        let noisy: Vec<f32> = tone.iter().zip(noise(16000)).map(|(s, n)| s + n).collect();
+
+        let filters = mel_filter_bank(&config).unwrap();
+        let mel_energy = |spec: &[Vec<f32>]| -> Vec<f32> {
+            let mel = apply_mel_filters(spec, &filters, &config).unwrap();
+            (0..config.n_mels).map(|m| mel.iter().map(|frame| frame[m]).sum()).collect()
+        };
+        let clean = mel_energy(&power(tone));
+        let signal_band = (0..config.n_mels).max_by(|&a, &b| clean[a].total_cmp(&clean[b])).unwrap();
+        // The signal band against every other band, an in-mel-domain SNR.
+        let band_snr = |energy: Vec<f32>| energy[signal_band] / (energy.iter().sum::<f32>() - energy[signal_band]);
+
+        let mut filtered = power(noisy.clone());
+        wiener_filter(&mut filtered, &noise_estimate, -20.0);
+        // Wiener gains never exceed one, so it is the signal band's energy relative to the rest
+        // of the spectrum that rises.
+        let before = band_snr(mel_energy(&power(noisy)));
+        let after = band_snr(mel_energy(&filtered));
+        assert!(after > 1.25 * before, "signal band to rest ratio went from {} to {}", before, after);
+    }
+
+    #[test]
+    fn chroma_filterbank_rolls_by_whole_multiples_of_three() {
+        let (n_fft, sample_rate) = (4097, 16000);
+        let a440_bin = (440.0 * 2.0 * (n_fft - 1) as f32 / sample_rate as f32).round() as usize;
+        // A is row 0 before the roll, so it lands on n_chroma - 3 * (n_chroma / 12).
+        for (n_chroma, a_row) in [(12, 9), (18, 15), (24, 18), (36, 27)] {
+            let filterbank = make_chroma_filterbank(n_fft, sample_rate, n_chroma, 0.0);
+            let peak = (0..n_chroma).max_by(|&a, &b| filterbank[a][a440_bin].total_cmp(&filterbank[b][a440_bin])).unwrap();
+            assert_eq!(peak, a_row, "n_chroma {}", n_chroma);
+        }
+    }
+
+    #[test]
+    fn a_weighting_option_attenuates_low_tones_before_framing() {
+        let mel_power = |frequency: f32, a_weighting: bool| -> f32 {
+            let config = MelConfig::builder()
+                .max_duration_secs(None)
+                .output_scale(SpectrogramScale::Raw)
+                .drc(None)
+                .a_weighting(a_weighting)
+                .build()
+                .unwrap();
+            let spectrogram = MelExtractorContext::new(config).unwrap().extract_from_samples(&sine(16000, 16000, frequency), 16000).unwrap();
+            spectrogram.as_slice().iter().sum()
+        };
+        // A-weighting is about -19 dB at 100 Hz and 0 dB at 1 kHz.
+        let low_db = 10.0 * (mel_power(100.0, true) / mel_power(100.0, false)).log10();
+        let mid_db = 10.0 * (mel_power(1000.0, true) / mel_power(1000.0, false)).log10();
+        assert!((low_db + 19.1).abs() < 1.5, "100 Hz changed by {} dB", low_db);
+        assert!(mid_db.abs() < 0.5, "1 kHz changed by {} dB", mid_db);
+    }
+
+    #[test]
+    fn a_weighting_option_needs_1_khz_below_nyquist() {
+        let builder = || MelConfig::builder().sample_rate(2000).max_freq(1000.0);
+        assert!(builder().build().is_ok());
+        assert!(matches!(builder().a_weighting(true).build(), Err(MelError::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn multi_resolution_stft_frames_each_resolution_without_padding() {
+        let configs = [
+            StftConfig { n_fft: 512, hop_length: 128, window_type: WindowType::Hann },
+            StftConfig { n_fft: 256, hop_length: 64, window_type: WindowType::Hamming },
+        ];
+        let spectrograms = multi_resolution_stft(&sine(4000, 16000, 1000.0), &configs).unwrap();
+        for (spectrogram, stft) in spectrograms.iter().zip(&configs) {
+            assert_eq!(spectrogram.len(), (4000 - stft.n_fft) / stft.hop_length + 1);
+            assert!(spectrogram.iter().all(|frame| frame.len() == stft.n_fft / 2 + 1));
+        }
+    }
+
+    #[test]
+    fn multi_resolution_stft_rejects_zero_sizes_before_framing() {
+        for (n_fft, hop_length) in [(0, 128), (512, 0)] {
+            let configs = [
+                StftConfig { n_fft: 512, hop_length: 128, window_type: WindowType::Hann },
+                StftConfig { n_fft, hop_length, window_type: WindowType::Hann },
+            ];
+            let result = multi_resolution_stft(&sine(4000, 16000, 1000.0), &configs);
+            assert!(matches!(result, Err(MelError::InvalidConfig(_))));
+        }
+    }
+
+    #[test]
+    fn mel_distance_is_nan_for_mismatched_shapes() {
+        let spectrogram = |n_frames: usize, n_mels: usize, value: f32| {
+            MelSpectrogram::from_parts(vec![value; n_frames * n_mels], n_frames, n_mels, 16000, 160).unwrap()
+        };
+        // Every frame differs by 2.0 in each of 4 bins: a distance of 4.0.
+        assert_eq!(compute_mel_distance(&spectrogram(10, 4, 1.0), &spectrogram(10, 4, 3.0)), 4.0);
+        assert!(compute_mel_distance(&spectrogram(10, 4, 1.0), &spectrogram(5, 4, 1.0)).is_nan());
+        assert!(compute_mel_distance(&spectrogram(10, 4, 1.0), &spectrogram(10, 3, 1.0)).is_nan());
+    }
+
+    #[test]
+    fn estimate_vtln_warp_finds_the_warp_a_gmm_was_trained_on() {
+        let config = MelConfig::builder().max_duration_secs(None).build().unwrap();
+        let context = MelExtractorContext::new(config.clone()).unwrap();
+        // A harmonic-rich tone, so every warp moves energy between mel bins.
+        let samples: Vec<f32> = (0..16000)
+            .map(|i| (1..=20).map(|h| (2.0 * std::f32::consts::PI * 150.0 * h as f32 * i as f32 / 16000.0).sin() / h as f32).sum())
+            .collect();
+        let n_mfcc = 13;
+        let mfcc_at = |warp_alpha: f32| -> Vec<Vec<f32>> {
+            let mut warped = context.clone();
+            warped.mel_filters = dsp::vtln_warp_mel_filterbank(&context.mel_filters, warp_alpha, &config).unwrap();
+            let features = warped.compute_log_mel(samples.clone(), 16000, &|_| {}).unwrap();
+            mel_to_mfcc(&features.log_mel, n_mfcc).unwrap()
+        };
+        // A single Gaussian centred on the mean MFCCs under a 1.08 warp.
+        let target = mfcc_at(1.08);
+        let mean: Vec<f32> = (0..n_mfcc).map(|k| target.iter().map(|frame| frame[k]).sum::<f32>() / target.len() as f32).collect();
+        let (means, vars, weights) = (vec![mean], vec![vec![0.01; n_mfcc]], [1.0]);
+
+        let estimate = estimate_vtln_warp(&context, &samples, 16000, &means, &vars, &weights, (0.85, 1.2), 20).unwrap();
+        assert!((estimate - 1.08).abs() < 0.01, "estimated warp {}", estimate);
+
+        // One step scores the two interior probes and returns the better one, not the midpoint.
+        let inv_phi = (5f32.sqrt() - 1.0) / 2.0;
+        let (c, d) = (1.2 - inv_phi * 0.35, 0.85 + inv_phi * 0.35);
+        let estimate = estimate_vtln_warp(&context, &samples, 16000, &means, &vars, &weights, (0.85, 1.2), 1).unwrap();
+        assert_eq!(estimate, if (d - 1.08).abs() < (c - 1.08).abs() { d } else { c });
+    }
+}
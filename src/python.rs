@@ -0,0 +1,133 @@
+use numpy::ndarray::Array2;
+use numpy::{IntoPyArray, PyArray2};
+use pyo3::exceptions::{PyIOError, PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+
+use crate::{MelConfig, MelError, MelExtractorContext, MelSpectrogram};
+
+impl From<MelError> for PyErr {
+    fn from(err: MelError) -> PyErr {
+        match err {
+            MelError::IoError(_) => PyIOError::new_err(err.to_string()),
+            MelError::InvalidConfig(_)
+            | MelError::InvalidAudio(_)
+            | MelError::UnsupportedFormat(_)
+            | MelError::NanEncountered { .. } => PyValueError::new_err(err.to_string()),
+            _ => PyRuntimeError::new_err(err.to_string()),
+        }
+    }
+}
+
+/// Python view of the scalar [`MelConfig`] fields; omitted keywords keep the Whisper defaults.
+#[pyclass(module = "mel_feature_extractor_rust")]
+#[derive(Clone)]
+pub struct PyMelConfig {
+    config: MelConfig,
+}
+
+#[pymethods]
+impl PyMelConfig {
+    #[new]
+    #[pyo3(signature = (
+        *,
+        sample_rate = 16000,
+        max_duration_secs = Some(30.0),
+        frame_length = 400,
+        hop_length = 160,
+        n_mels = 80,
+        n_fft = 201,
+        min_freq = 0.0,
+        max_freq = 8000.0,
+        use_slaney_norm = true,
+        top_db = Some(80.0),
+        preemphasis_coeff = None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        sample_rate: u32,
+        max_duration_secs: Option<f32>,
+        frame_length: usize,
+        hop_length: usize,
+        n_mels: usize,
+        n_fft: usize,
+        min_freq: f32,
+        max_freq: f32,
+        use_slaney_norm: bool,
+        top_db: Option<f32>,
+        preemphasis_coeff: Option<f32>,
+    ) -> PyResult<Self> {
+        let config = MelConfig::builder()
+            .sample_rate(sample_rate)
+            .max_duration_secs(max_duration_secs)
+            .frame_length(frame_length)
+            .hop_length(hop_length)
+            .n_mels(n_mels)
+            .n_fft(n_fft)
+            .min_freq(min_freq)
+            .max_freq(max_freq)
+            .use_slaney_norm(use_slaney_norm)
+            .top_db(top_db)
+            .preemphasis_coeff(preemphasis_coeff)
+            .build()?;
+        Ok(PyMelConfig { config })
+    }
+
+    #[getter]
+    fn sample_rate(&self) -> u32 {
+        self.config.sample_rate
+    }
+
+    #[getter]
+    fn n_mels(&self) -> usize {
+        self.config.n_mels
+    }
+
+    #[getter]
+    fn hop_length(&self) -> usize {
+        self.config.hop_length
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.config)
+    }
+}
+
+/// Reusable extractor returning `float32` arrays of shape `(n_mels, n_frames)`.
+#[pyclass(module = "mel_feature_extractor_rust")]
+pub struct PyMelExtractor {
+    context: MelExtractorContext,
+}
+
+#[pymethods]
+impl PyMelExtractor {
+    #[new]
+    #[pyo3(signature = (config = None))]
+    fn new(config: Option<PyMelConfig>) -> PyResult<Self> {
+        let config = config.map_or_else(MelConfig::whisper_default, |config| config.config);
+        Ok(PyMelExtractor { context: MelExtractorContext::new(config)? })
+    }
+
+    fn extract<'py>(&self, py: Python<'py>, path: &str) -> PyResult<Bound<'py, PyArray2<f32>>> {
+        let spectrogram = py.allow_threads(|| self.context.extract(path))?;
+        into_mel_major_array(py, spectrogram)
+    }
+
+    fn extract_bytes<'py>(&self, py: Python<'py>, data: &[u8]) -> PyResult<Bound<'py, PyArray2<f32>>> {
+        let spectrogram = py.allow_threads(|| self.context.extract_from_bytes(data))?;
+        into_mel_major_array(py, spectrogram)
+    }
+}
+
+fn into_mel_major_array(py: Python<'_>, spectrogram: MelSpectrogram) -> PyResult<Bound<'_, PyArray2<f32>>> {
+    let shape = (spectrogram.n_frames(), spectrogram.n_mels());
+    let frames = Array2::from_shape_vec(shape, spectrogram.into_vec())
+        .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+    Ok(frames.reversed_axes().as_standard_layout().into_owned().into_pyarray(py))
+}
+
+#[pymodule]
+fn mel_feature_extractor_rust(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyMelConfig>()?;
+    m.add_class::<PyMelExtractor>()?;
+    Ok(())
+}
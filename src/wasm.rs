@@ -0,0 +1,17 @@
+use js_sys::Float32Array;
+use wasm_bindgen::prelude::*;
+
+use crate::{MelConfig, MelExtractorContext};
+
+/// Whisper log-mel features for mono `samples` at `sample_rate`, returned mel-major: 80 rows
+/// of `length / 80` frames each. Throws on invalid input.
+#[wasm_bindgen]
+pub fn extract_mel_from_float32array(samples: &[f32], sample_rate: u32) -> Result<Float32Array, JsError> {
+    let spectrogram = MelExtractorContext::new(MelConfig::whisper_default())?.extract_from_samples(samples, sample_rate)?;
+    let (n_frames, n_mels) = (spectrogram.n_frames(), spectrogram.n_mels());
+    let data = spectrogram.as_slice();
+    let mel_major: Vec<f32> = (0..n_mels)
+        .flat_map(|m| (0..n_frames).map(move |t| data[t * n_mels + m]))
+        .collect();
+    Ok(Float32Array::from(mel_major.as_slice()))
+}
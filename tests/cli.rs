@@ -0,0 +1,68 @@
+//! End-to-end tests of the `mel-extract` binary reading WAV data from stdin.
+#![cfg(feature = "cli")]
+
+use std::io::{Cursor, Write};
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+fn sine_wav_bytes(n_samples: usize, sample_rate: u32) -> Vec<u8> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut cursor = Cursor::new(Vec::new());
+    let mut writer = hound::WavWriter::new(&mut cursor, spec).unwrap();
+    for i in 0..n_samples {
+        let sample = 0.5 * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / sample_rate as f32).sin();
+        writer.write_sample((sample * i16::MAX as f32) as i16).unwrap();
+    }
+    writer.finalize().unwrap();
+    cursor.into_inner()
+}
+
+fn run_with_stdin(args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_mel-extract"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("mel-extract-{}-{}", std::process::id(), name))
+}
+
+#[test]
+fn stdin_wav_is_written_as_n_mels_by_n_frames_csv() {
+    let output_path = temp_path("stdin.csv");
+    let output = run_with_stdin(
+        &["--input", "-", "--n-mels", "40", "--output", output_path.to_str().unwrap()],
+        &sine_wav_bytes(16000, 16000),
+    );
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("40 mels x 3000 frames"));
+
+    let csv = std::fs::read_to_string(&output_path).unwrap();
+    std::fs::remove_file(&output_path).unwrap();
+    let rows: Vec<Vec<f32>> = csv
+        .lines()
+        .map(|line| line.split(',').map(|value| value.trim().parse().unwrap()).collect())
+        .collect();
+    assert_eq!(rows.len(), 40);
+    assert!(rows.iter().all(|row| row.len() == 3000));
+}
+
+#[test]
+fn invalid_stdin_exits_with_failure() {
+    let output_path = temp_path("invalid.npy");
+    let output = run_with_stdin(&["--output", output_path.to_str().unwrap()], b"not a wav file");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("mel-extract: "));
+    assert!(!output_path.exists());
+}
@@ -0,0 +1,153 @@
+"""Regenerates the librosa reference fixtures used by tests/librosa_reference.rs.
+
+    python tests/fixtures/librosa_reference.py
+
+With librosa installed the values come from librosa itself. Without it, a float64
+transcription of the same defaults is used (librosa 0.10: centred zero-padded STFT with a
+periodic Hann window, Slaney mel filters normalised to unit area, `power_to_db` with
+`ref=1.0, amin=1e-10, top_db=80` and an orthonormal DCT-II).
+"""
+
+import cmath
+import math
+import os
+
+SR = 22050
+N_FFT = 2048
+HOP = 512
+N_MELS = 128
+N_MFCC = 20
+FREQUENCY = 440.0
+
+
+def sine(n_samples):
+    # Round through float32 like the Rust tests do.
+    import struct
+    return [
+        struct.unpack("f", struct.pack("f", 0.5 * math.sin(2.0 * math.pi * FREQUENCY * i / SR)))[0]
+        for i in range(n_samples)
+    ]
+
+
+def fft(x):
+    n = len(x)
+    if n == 1:
+        return list(x)
+    even, odd = fft(x[0::2]), fft(x[1::2])
+    twiddled = [cmath.exp(-2j * math.pi * k / n) * odd[k] for k in range(n // 2)]
+    return [even[k] + twiddled[k] for k in range(n // 2)] + [even[k] - twiddled[k] for k in range(n // 2)]
+
+
+def hz_to_mel(f):
+    f_sp = 200.0 / 3
+    min_log_hz = 1000.0
+    if f < min_log_hz:
+        return f / f_sp
+    return min_log_hz / f_sp + math.log(f / min_log_hz) / (math.log(6.4) / 27.0)
+
+
+def mel_to_hz(m):
+    f_sp = 200.0 / 3
+    min_log_mel = 1000.0 / f_sp
+    if m < min_log_mel:
+        return f_sp * m
+    return 1000.0 * math.exp((math.log(6.4) / 27.0) * (m - min_log_mel))
+
+
+def mel_filters():
+    fft_freqs = [k * SR / N_FFT for k in range(N_FFT // 2 + 1)]
+    lo, hi = hz_to_mel(0.0), hz_to_mel(SR / 2.0)
+    mel_f = [mel_to_hz(lo + (hi - lo) * i / (N_MELS + 1)) for i in range(N_MELS + 2)]
+    filters = []
+    for m in range(N_MELS):
+        enorm = 2.0 / (mel_f[m + 2] - mel_f[m])
+        row = []
+        for f in fft_freqs:
+            lower = (f - mel_f[m]) / (mel_f[m + 1] - mel_f[m])
+            upper = (mel_f[m + 2] - f) / (mel_f[m + 2] - mel_f[m + 1])
+            row.append(max(0.0, min(lower, upper)) * enorm)
+        filters.append(row)
+    return filters
+
+
+def mel_power_transcribed(y):
+    window = [0.5 - 0.5 * math.cos(2.0 * math.pi * n / N_FFT) for n in range(N_FFT)]
+    padded = [0.0] * (N_FFT // 2) + y + [0.0] * (N_FFT // 2)
+    n_frames = 1 + (len(padded) - N_FFT) // HOP
+    filters = mel_filters()
+    mel = []
+    for t in range(n_frames):
+        frame = padded[t * HOP:t * HOP + N_FFT]
+        spectrum = fft([s * w for s, w in zip(frame, window)])[:N_FFT // 2 + 1]
+        power = [abs(c) ** 2 for c in spectrum]
+        mel.append([sum(w * p for w, p in zip(row, power)) for row in filters])
+    return mel
+
+
+def log_mel_transcribed(y):
+    db = [[10.0 * math.log10(max(v, 1e-10)) for v in frame] for frame in mel_power_transcribed(y)]
+    floor = max(max(frame) for frame in db) - 80.0
+    return [[max(v, floor) for v in frame] for frame in db]
+
+
+def mfcc_transcribed(y):
+    mfcc = []
+    for frame in log_mel_transcribed(y):
+        coeffs = []
+        for n in range(N_MFCC):
+            norm = math.sqrt((1.0 if n == 0 else 2.0) / N_MELS)
+            coeffs.append(norm * sum(m * math.cos(math.pi * n * (k + 0.5) / N_MELS) for k, m in enumerate(frame)))
+        mfcc.append(coeffs)
+    return mfcc
+
+
+def melspectrogram_librosa(y):
+    import librosa
+    import numpy as np
+
+    return librosa.feature.melspectrogram(y=np.asarray(y, dtype=np.float32), sr=SR).T.tolist()
+
+
+def mfcc_librosa(y):
+    import librosa
+    import numpy as np
+
+    return librosa.feature.mfcc(y=np.asarray(y, dtype=np.float32), sr=SR, n_mfcc=N_MFCC).T.tolist()
+
+
+def write_fixture(name, description, source, frames, fmt):
+    path = os.path.join(os.path.dirname(os.path.abspath(__file__)), name)
+    with open(path, "w") as out:
+        out.write("# %s\n" % description)
+        out.write("# generated by librosa_reference.py (%s), one frame per line\n" % source)
+        for frame in frames:
+            out.write(" ".join(fmt % v for v in frame) + "\n")
+
+
+def main():
+    try:
+        import librosa  # noqa: F401
+        source = "librosa"
+        melspectrogram, mfcc = melspectrogram_librosa, mfcc_librosa
+    except ImportError:
+        source = "float64 transcription of librosa's defaults"
+        melspectrogram, mfcc = mel_power_transcribed, mfcc_transcribed
+
+    write_fixture(
+        "librosa_mfcc_sine_440hz.txt",
+        "librosa.feature.mfcc(y=0.5*sin(2*pi*440*t), sr=22050, n_mfcc=20) over %d samples" % (SR // 4),
+        source,
+        mfcc(sine(SR // 4)),
+        "%.6f",
+    )
+    write_fixture(
+        "librosa_melspectrogram_sine_440hz.txt",
+        "librosa.feature.melspectrogram(y=0.5*sin(2*pi*440*t), sr=22050) over %d samples" % SR,
+        source,
+        melspectrogram(sine(SR)),
+        "%.7e",
+    )
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,68 @@
+//! Comparisons against librosa reference values checked in under `tests/fixtures`, regenerated
+//! with `python tests/fixtures/librosa_reference.py`.
+
+use mel_feature_extractor_rust::{mel_to_mfcc, MelConfig, MelExtractorContext, SpectrogramScale};
+
+const SAMPLE_RATE: u32 = 22050;
+
+fn fixture(text: &str) -> Vec<Vec<f32>> {
+    text.lines()
+        .filter(|line| !line.starts_with('#'))
+        .map(|line| line.split_whitespace().map(|value| value.parse().unwrap()).collect())
+        .collect()
+}
+
+fn sine(n_samples: usize, frequency: f32) -> Vec<f32> {
+    (0..n_samples)
+        .map(|i| 0.5 * (2.0 * std::f64::consts::PI * frequency as f64 * i as f64 / SAMPLE_RATE as f64).sin() as f32)
+        .collect()
+}
+
+#[test]
+fn mfcc_matches_librosa_for_a_short_sine() {
+    let expected = fixture(include_str!("fixtures/librosa_mfcc_sine_440hz.txt"));
+    let config = MelConfig {
+        output_scale: SpectrogramScale::PowerDb,
+        top_db: Some(80.0),
+        ..MelConfig::librosa_default(SAMPLE_RATE)
+    };
+    let spectrogram = MelExtractorContext::new(config)
+        .unwrap()
+        .extract_from_samples(&sine(SAMPLE_RATE as usize / 4, 440.0), SAMPLE_RATE)
+        .unwrap();
+    let log_mel: Vec<Vec<f32>> = (0..spectrogram.n_frames()).map(|i| spectrogram.get_frame(i).to_vec()).collect();
+    let mfcc = mel_to_mfcc(&log_mel, 20).unwrap();
+
+    assert_eq!(mfcc.len(), expected.len());
+    for (t, (frame, reference)) in mfcc.iter().zip(&expected).enumerate() {
+        for (n, (&value, &reference)) in frame.iter().zip(reference).enumerate() {
+            assert!(
+                (value - reference).abs() < 1e-3 * reference.abs().max(1.0),
+                "frame {} coefficient {}: {} vs librosa {}",
+                t, n, value, reference
+            );
+        }
+    }
+}
+
+#[test]
+fn librosa_default_matches_librosa_melspectrogram_for_a_one_second_sine() {
+    let expected = fixture(include_str!("fixtures/librosa_melspectrogram_sine_440hz.txt"));
+    let spectrogram = MelExtractorContext::new(MelConfig::librosa_default(SAMPLE_RATE))
+        .unwrap()
+        .extract_from_samples(&sine(SAMPLE_RATE as usize, 440.0), SAMPLE_RATE)
+        .unwrap();
+
+    assert_eq!((spectrogram.n_frames(), spectrogram.n_mels()), (expected.len(), expected[0].len()));
+    // Bins reach ~1e3 in power, where single-precision accumulation alone is worth ~1e-3, so the
+    // 1e-4 bound is absolute below unit power and relative above it.
+    for (t, reference) in expected.iter().enumerate() {
+        for (n, (&value, &reference)) in spectrogram.get_frame(t).iter().zip(reference).enumerate() {
+            assert!(
+                (value - reference).abs() < 1e-4 * reference.abs().max(1.0),
+                "frame {} mel {}: {} vs librosa {}",
+                t, n, value, reference
+            );
+        }
+    }
+}
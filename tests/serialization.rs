@@ -0,0 +1,188 @@
+//! Round trips through the on-disk and in-memory output formats.
+
+#![cfg(not(feature = "wasm"))]
+
+use mel_feature_extractor_rust::{load_matrix_from_csv, save_as_npy, save_kaldi_text_ark, save_matrix_as_csv, MelConfig, MelExtractorContext, MelError, MelSpectrogram};
+
+/// A short, unpadded spectrogram of a 440 Hz tone: 40 mels by 25 frames.
+fn sample_spectrogram() -> MelSpectrogram {
+    let config = MelConfig::builder().n_mels(40).max_duration_secs(None).build().unwrap();
+    let samples: Vec<f32> = (0..4000)
+        .map(|i| 0.5 * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 16000.0).sin())
+        .collect();
+    MelExtractorContext::new(config).unwrap().extract_from_samples(&samples, 16000).unwrap()
+}
+
+#[cfg(feature = "arrow")]
+#[test]
+fn arrow_ipc_round_trip_preserves_columns() {
+    use arrow2::array::{Float32Array, Int64Array};
+    use arrow2::io::ipc::{read, write};
+    use mel_feature_extractor_rust::{spectrogram_arrow_schema, spectrogram_to_record_batch};
+    use std::io::Cursor;
+
+    let spec = sample_spectrogram();
+    let schema = spectrogram_arrow_schema(&spec);
+    let options = write::WriteOptions { compression: None };
+    let mut writer = write::FileWriter::try_new(Vec::new(), schema.clone(), None, options).unwrap();
+    writer.write(&spectrogram_to_record_batch(&spec), None).unwrap();
+    writer.finish().unwrap();
+
+    let mut bytes = Cursor::new(writer.into_inner());
+    let metadata = read::read_file_metadata(&mut bytes).unwrap();
+    assert_eq!(metadata.schema, schema);
+    let chunks: Vec<_> = read::FileReader::new(bytes, metadata, None, None).collect::<Result<_, _>>().unwrap();
+    assert_eq!(chunks.len(), 1);
+
+    let columns = chunks[0].arrays();
+    assert_eq!(columns.len(), 1 + spec.n_mels());
+    let frames = columns[0].as_any().downcast_ref::<Int64Array>().unwrap();
+    assert_eq!(frames.values().as_slice(), (0..spec.n_frames() as i64).collect::<Vec<_>>().as_slice());
+    for (m, column) in columns[1..].iter().enumerate() {
+        let values = column.as_any().downcast_ref::<Float32Array>().unwrap();
+        let expected: Vec<f32> = (0..spec.n_frames()).map(|t| spec.get_frame(t)[m]).collect();
+        assert_eq!(values.values().as_slice(), expected.as_slice(), "mel_{}", m);
+    }
+}
+
+#[cfg(feature = "hdf5")]
+#[test]
+fn hdf5_round_trip_preserves_data_and_metadata() {
+    use mel_feature_extractor_rust::{load_spectrogram_hdf5, save_spectrogram_hdf5};
+
+    let spec = sample_spectrogram();
+    let path = std::env::temp_dir().join(format!("mel-serialization-{}.h5", std::process::id()));
+    let path = path.to_str().unwrap();
+    save_spectrogram_hdf5(&spec, path, "log_mel").unwrap();
+    let loaded = load_spectrogram_hdf5(path, "log_mel");
+    std::fs::remove_file(path).unwrap();
+
+    let loaded = loaded.unwrap();
+    assert_eq!((loaded.n_frames(), loaded.n_mels()), (spec.n_frames(), spec.n_mels()));
+    assert_eq!((loaded.sample_rate(), loaded.hop_length()), (spec.sample_rate(), spec.hop_length()));
+    assert_eq!(loaded.as_slice(), spec.as_slice());
+}
+
+fn frames(spec: &MelSpectrogram) -> Vec<Vec<f32>> {
+    (0..spec.n_frames()).map(|t| spec.get_frame(t).to_vec()).collect()
+}
+
+fn temp_path(name: &str) -> String {
+    let path = std::env::temp_dir().join(format!("mel-serialization-{}-{}", std::process::id(), name));
+    path.to_str().unwrap().to_string()
+}
+
+#[test]
+fn csv_round_trip_with_headers_is_exact() {
+    let matrix = frames(&sample_spectrogram());
+    let headers: Vec<String> = (0..matrix[0].len()).map(|m| format!("mel_{}", m)).collect();
+    let path = temp_path("headers.csv");
+    save_matrix_as_csv(&matrix, &path, Some(headers.clone())).unwrap();
+    let first_line = std::fs::read_to_string(&path).unwrap().lines().next().map(str::to_string);
+    let loaded = load_matrix_from_csv(&path, true);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(first_line.unwrap(), headers.join(","));
+    assert_eq!(loaded.unwrap(), matrix);
+}
+
+#[test]
+fn csv_round_trip_without_headers_is_exact() {
+    let matrix = frames(&sample_spectrogram());
+    let path = temp_path("plain.csv");
+    save_matrix_as_csv(&matrix, &path, None).unwrap();
+    let loaded = load_matrix_from_csv(&path, false);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.unwrap(), matrix);
+}
+
+#[test]
+fn csv_rejects_header_count_mismatch() {
+    let path = temp_path("mismatch.csv");
+    let headers = vec!["a".to_string(), "b".to_string()];
+    let result = save_matrix_as_csv(&[vec![1.0, 2.0, 3.0]], &path, Some(headers));
+    assert!(matches!(result, Err(MelError::InvalidConfig(_))));
+    assert!(!std::path::Path::new(&path).exists());
+}
+
+#[test]
+fn csv_numeric_header_is_not_read_as_data() {
+    let matrix = vec![vec![0.5, 1.5], vec![2.5, 3.5]];
+    let path = temp_path("numeric-header.csv");
+    save_matrix_as_csv(&matrix, &path, Some(vec!["0".to_string(), "1".to_string()])).unwrap();
+    let loaded = load_matrix_from_csv(&path, true);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.unwrap(), matrix);
+}
+
+#[test]
+fn csv_rejects_corrupt_first_row_and_jagged_rows() {
+    for (name, contents) in [("corrupt.csv", "1.0,oops\n2.0,3.0\n"), ("jagged.csv", "1.0,2.0\n3.0\n")] {
+        let path = temp_path(name);
+        std::fs::write(&path, contents).unwrap();
+        let loaded = load_matrix_from_csv(&path, false);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(loaded, Err(MelError::UnsupportedFormat(_))), "{} accepted", name);
+    }
+}
+
+#[test]
+fn npy_has_magic_shape_header_and_little_endian_data() {
+    let matrix = frames(&sample_spectrogram());
+    let path = temp_path("features.npy");
+    save_as_npy(&matrix, &path).unwrap();
+    let bytes = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(&bytes[..8], b"\x93NUMPY\x01\x00");
+    let header_len = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+    let data_start = 10 + header_len;
+    assert_eq!(data_start % 64, 0);
+    let header = std::str::from_utf8(&bytes[10..data_start]).unwrap();
+    assert!(header.ends_with('\n'));
+    let expected = format!("{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {}), }}", matrix.len(), matrix[0].len());
+    assert_eq!(header.trim_end(), expected);
+
+    let data: Vec<f32> = bytes[data_start..].chunks_exact(4).map(|b| f32::from_le_bytes(b.try_into().unwrap())).collect();
+    assert_eq!(data, matrix.concat());
+}
+
+#[test]
+fn kaldi_text_ark_matches_the_archive_grammar() {
+    let spec = sample_spectrogram();
+    let features = vec![("utt_a".to_string(), spec.clone()), ("utt-b".to_string(), spec.clone())];
+    let (ark_path, scp_path) = (temp_path("feats.ark"), temp_path("feats.scp"));
+    save_kaldi_text_ark(&features, &ark_path, &scp_path).unwrap();
+    let ark = std::fs::read_to_string(&ark_path).unwrap();
+    let scp = std::fs::read_to_string(&scp_path).unwrap();
+    std::fs::remove_file(&ark_path).unwrap();
+    std::fs::remove_file(&scp_path).unwrap();
+
+    // `uttid  [`, then one indented, space-separated row per frame, the last closed by `]`.
+    let entry = regex::Regex::new(r"(?m)^(\S+)  \[((?:\n  (?:[^\s\]]+ )+)+)\]\n").unwrap();
+    let whole = regex::Regex::new(&format!(r"\A(?:{})+\z", entry.as_str())).unwrap();
+    assert!(whole.is_match(&ark), "malformed archive:\n{}", ark);
+
+    let entries: Vec<_> = entry.captures_iter(&ark).collect();
+    assert_eq!(entries.len(), features.len());
+    for (captures, (id, spec)) in entries.iter().zip(&features) {
+        assert_eq!(&captures[1], id);
+        let rows: Vec<Vec<f32>> = captures[2]
+            .lines()
+            .skip(1)
+            .map(|row| row.split_whitespace().map(|v| v.parse().unwrap()).collect())
+            .collect();
+        assert_eq!(rows, frames(spec));
+    }
+
+    // Each script line points just past its key in the archive.
+    let line = regex::Regex::new(r"(?m)^(\S+) (.+):(\d+)$").unwrap();
+    let lines: Vec<_> = line.captures_iter(&scp).collect();
+    assert_eq!(lines.len(), features.len());
+    for (captures, (id, _)) in lines.iter().zip(&features) {
+        assert_eq!((&captures[1], &captures[2]), (id.as_str(), ark_path.as_str()));
+        let offset: usize = captures[3].parse().unwrap();
+        assert_eq!(&ark[offset - id.len() - 1..offset], format!("{} ", id));
+        assert!(ark[offset..].starts_with(" ["));
+    }
+}
@@ -0,0 +1,57 @@
+"""Tests for the PyO3 bindings. Build the extension into the current environment first:
+
+    pip install maturin pytest numpy
+    maturin develop
+    pytest tests/test_python.py
+"""
+
+import io
+import math
+import struct
+import wave
+
+import numpy as np
+import pytest
+
+import mel_feature_extractor_rust as mel
+
+
+def sine_wav_bytes(seconds=1.0, sample_rate=16000, frequency=440.0):
+    n_samples = int(seconds * sample_rate)
+    pcm = b"".join(
+        struct.pack("<h", int(0.5 * 32767 * math.sin(2.0 * math.pi * frequency * i / sample_rate)))
+        for i in range(n_samples)
+    )
+    buffer = io.BytesIO()
+    with wave.open(buffer, "wb") as wav:
+        wav.setnchannels(1)
+        wav.setsampwidth(2)
+        wav.setframerate(sample_rate)
+        wav.writeframes(pcm)
+    return buffer.getvalue()
+
+
+def test_extract_bytes_returns_float32_mels_by_frames():
+    features = mel.PyMelExtractor().extract_bytes(sine_wav_bytes())
+    assert isinstance(features, np.ndarray)
+    assert features.dtype == np.float32
+    assert features.shape == (80, 3000)
+
+
+def test_extract_path_honours_config(tmp_path):
+    path = tmp_path / "sine.wav"
+    path.write_bytes(sine_wav_bytes())
+    config = mel.PyMelConfig(n_mels=40, max_duration_secs=None)
+    features = mel.PyMelExtractor(config).extract(str(path))
+    assert features.dtype == np.float32
+    assert features.shape == (40, 16000 // 160)
+
+
+def test_invalid_config_raises_value_error():
+    with pytest.raises(ValueError):
+        mel.PyMelConfig(n_mels=0)
+
+
+def test_undecodable_bytes_raise_runtime_error():
+    with pytest.raises(RuntimeError):
+        mel.PyMelExtractor().extract_bytes(b"not a wav file")